@@ -1,10 +1,22 @@
+mod timings;
+
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process;
 
 use anyhow::{Context, Result};
-use clap::{ArgAction, Parser};
-use png2svg_core::{png_to_svg, VectorizeMode, VectorizeOptions};
+use clap::parser::ValueSource;
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser};
+use png2svg_core::{
+    decode_animation, emit::type_name_from, mask_to_coco, optimize_with, BackgroundOptions,
+    CocoOptions, ComposeEmitter, DxfEmitter, Emitter, GcodeEmitter, GeoJsonEmitter, HpglEmitter,
+    LabelMask, LaserProfile, Objective, OptimizeTarget, OutputFormat, PenMap, Pipeline,
+    ReactColors, ReactEmitter, SwiftUiEmitter, VectorizeMode, VectorizeOptions, Vectorizer,
+    WorldFile,
+};
+use tracing_subscriber::layer::SubscriberExt;
+
+use crate::timings::TimingLayer;
 
 /// Minimal CLI wrapper around the png2svg core engine.
 #[derive(Parser, Debug)]
@@ -16,9 +28,77 @@ use png2svg_core::{png_to_svg, VectorizeMode, VectorizeOptions};
 struct Cli {
     /// Path to the input PNG file.
     input: PathBuf,
-    /// Optional path to write the SVG output. Defaults to stdout.
+    /// Optional path to write the output. Defaults to stdout.
     #[arg(short, long)]
     output: Option<PathBuf>,
+    /// Output format; inferred from the --output extension when omitted, otherwise SVG.
+    #[arg(
+        short = 'f',
+        long,
+        value_parser = parse_format,
+        value_name = "FORMAT",
+        help = "Output format: svg, pdf, eps, dxf, hpgl, gcode, android, react, swiftui, compose, \
+                lottie or geojson. Defaults to the --output extension, or svg."
+    )]
+    format: Option<OutputFormat>,
+    /// Source resolution used to convert pixels to physical units in DXF, HPGL and G-code output.
+    #[arg(
+        long,
+        default_value_t = 96.0,
+        value_parser = parse_dpi,
+        help = "Pixels per inch for DXF/HPGL/G-code physical scaling (1-10000)."
+    )]
+    dpi: f32,
+    /// JSON color-to-pen assignment for HPGL output; created if missing and updated with new colors.
+    #[arg(long, value_name = "FILE")]
+    pen_map: Option<PathBuf>,
+    /// Maximum chord error, in pixels, when flattening curves for DXF, HPGL, G-code and GeoJSON output.
+    #[arg(
+        long,
+        default_value_t = 0.25,
+        value_parser = parse_chord_tolerance,
+        help = "Curve flattening tolerance in pixels for DXF/HPGL/G-code/GeoJSON output (0.01-10.0)."
+    )]
+    chord_tolerance: f32,
+    /// JSON per-color laser feed/power settings for G-code output.
+    #[arg(long, value_name = "FILE")]
+    laser_profile: Option<PathBuf>,
+    /// Distance between G-code hatch lines in millimetres.
+    #[arg(
+        long,
+        default_value_t = 0.1,
+        value_parser = parse_hatch_spacing,
+        help = "Hatch line spacing in mm for G-code fills (0.01-100)."
+    )]
+    hatch_spacing: f32,
+    /// Hatch direction in degrees, counter-clockwise from the X axis.
+    #[arg(
+        long,
+        default_value_t = 0.0,
+        value_parser = parse_hatch_angle,
+        allow_negative_numbers = true,
+        help = "Hatch angle in degrees for G-code fills (-180-180)."
+    )]
+    hatch_angle: f32,
+    /// How React components color their paths.
+    #[arg(
+        long,
+        default_value = "fixed",
+        value_parser = parse_react_colors,
+        value_name = "fixed|props|current",
+        help = "React output: inline colors, colorN props, or currentColor."
+    )]
+    react_colors: ReactColors,
+    /// Treat the input as a segmentation mask and write COCO annotations instead of vectors.
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        help = "Read the input as a label mask (pixel value = class ID) and write COCO polygon JSON."
+    )]
+    coco: bool,
+    /// Class ID left unannotated in COCO output; every class is annotated if omitted.
+    #[arg(long, value_name = "ID")]
+    mask_background: Option<u32>,
     /// Number of colors to quantize the image to.
     #[arg(
         short = 'c',
@@ -60,13 +140,222 @@ struct Cli {
         long,
         default_value = "logo",
         value_parser = parse_mode,
-        value_name = "logo|poster|pixel",
-        help = "Preset tuned for logo, poster, or pixel-art inputs."
+        value_name = "logo|poster|pixel|auto",
+        help = "Preset tuned for logo, poster, or pixel-art inputs; auto picks one (and the tuning) from the image."
     )]
     mode: VectorizeMode,
+    /// Alpha at or below which pixels count as transparent.
+    #[arg(
+        long,
+        default_value_t = 0,
+        value_parser = parse_alpha_threshold,
+        help = "Pixels with alpha at or below this are left out; the rest keep their alpha (0-254)."
+    )]
+    alpha_threshold: u8,
+    /// Background color to remove before tracing.
+    #[arg(
+        long,
+        value_name = "auto|RRGGBB",
+        help = "Make the background transparent: detect the dominant border color, or give one as hex."
+    )]
+    background: Option<BackgroundOptions>,
+    /// How far a color may stray from the background color and still be removed.
+    #[arg(
+        long,
+        default_value_t = 16,
+        value_name = "0-255",
+        help = "Largest per-channel difference from the background color that is still removed."
+    )]
+    background_tolerance: u8,
+    /// Keep the removed background as a single full-size rectangle.
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        requires = "background",
+        help = "Emit the removed background as one rectangle behind the traced shapes."
+    )]
+    keep_background: bool,
+    /// Un-blend edge pixels from the background before it is removed.
+    #[arg(
+        long,
+        action = ArgAction::SetTrue,
+        requires = "background",
+        help = "Recover foreground color and alpha of edge pixels blended with the background, so no halo is left."
+    )]
+    unmatte: bool,
+    /// Largest number of path nodes to aim for.
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Search colors, mode and tolerance for output with at most this many path nodes."
+    )]
+    max_nodes: Option<usize>,
+    /// Largest number of paths to aim for.
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Search the options for output with at most this many paths."
+    )]
+    max_paths: Option<usize>,
+    /// Largest output size to aim for.
+    #[arg(
+        long,
+        value_name = "BYTES",
+        help = "Search the options for output of at most this many bytes, in the chosen --format."
+    )]
+    max_bytes: Option<usize>,
+    /// Largest reconstruction error to accept.
+    #[arg(
+        long,
+        value_name = "RMSE",
+        help = "Search the options for output whose rendering differs from the input by at most this RMS error (0-255)."
+    )]
+    max_error: Option<f32>,
+    /// What the option search minimizes within the limits.
+    #[arg(
+        long,
+        value_name = "error|nodes|bytes",
+        help = "What to make as small as possible while meeting the --max-* limits (default: error)."
+    )]
+    minimize: Option<Objective>,
     /// Print debug info about the parsed options.
     #[arg(long, action = ArgAction::SetTrue)]
     debug: bool,
+    /// Write intermediate stage images (quantized, labels, overlay) into this directory.
+    #[arg(long, value_name = "DIR")]
+    debug_dir: Option<PathBuf>,
+    /// Print a per-stage timing table to stderr.
+    #[arg(long, action = ArgAction::SetTrue)]
+    timings: bool,
+}
+
+fn emitter_for(
+    format: OutputFormat,
+    cli: &Cli,
+    vectorizer: &mut Vectorizer,
+) -> Result<Box<dyn Emitter>> {
+    Ok(match format {
+        OutputFormat::Dxf => Box::new(DxfEmitter {
+            dpi: Some(cli.dpi),
+            flatten_tolerance: cli.chord_tolerance,
+        }),
+        OutputFormat::Hpgl => Box::new(HpglEmitter {
+            pen_map: load_pen_map(cli.pen_map.as_deref(), vectorizer)?,
+            dpi: cli.dpi,
+            flatten_tolerance: cli.chord_tolerance,
+        }),
+        OutputFormat::Gcode => Box::new(GcodeEmitter {
+            profile: load_laser_profile(cli.laser_profile.as_deref())?,
+            dpi: cli.dpi,
+            hatch_spacing: cli.hatch_spacing,
+            hatch_angle: cli.hatch_angle,
+            flatten_tolerance: cli.chord_tolerance,
+        }),
+        OutputFormat::React => {
+            let jsx = cli
+                .output
+                .as_deref()
+                .and_then(Path::extension)
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("jsx"));
+            Box::new(ReactEmitter {
+                component_name: type_name(cli),
+                typescript: !jsx,
+                colors: cli.react_colors,
+            })
+        }
+        OutputFormat::SwiftUi => Box::new(SwiftUiEmitter {
+            type_name: type_name(cli),
+        }),
+        OutputFormat::Compose => Box::new(ComposeEmitter {
+            name: type_name(cli),
+        }),
+        OutputFormat::GeoJson => Box::new(GeoJsonEmitter {
+            world_file: load_world_file(&cli.input)?,
+            flatten_tolerance: cli.chord_tolerance,
+        }),
+        other => other.emitter(),
+    })
+}
+
+/// Type name for generated source, taken from the output file name.
+fn type_name(cli: &Cli) -> String {
+    let stem = cli
+        .output
+        .as_deref()
+        .and_then(Path::file_stem)
+        .and_then(|stem| stem.to_str());
+    stem.map(type_name_from)
+        .unwrap_or_else(|| "VectorImage".into())
+}
+
+/// Reads the world file next to `input` (`map.pgw`, `map.pngw` or `map.wld`), if there is one.
+fn load_world_file(input: &Path) -> Result<Option<WorldFile>> {
+    let extension = input
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default();
+    for sidecar in WorldFile::sidecar_extensions(extension) {
+        let path = input.with_extension(&sidecar);
+        let path = if path.exists() {
+            path
+        } else {
+            input.with_extension(sidecar.to_ascii_uppercase())
+        };
+        if !path.exists() {
+            continue;
+        }
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let world_file = text
+            .parse()
+            .map_err(anyhow::Error::msg)
+            .with_context(|| format!("invalid world file {}", path.display()))?;
+        return Ok(Some(world_file));
+    }
+    Ok(None)
+}
+
+fn load_laser_profile(path: Option<&Path>) -> Result<LaserProfile> {
+    let Some(path) = path else {
+        return Ok(LaserProfile::default());
+    };
+    let json =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    serde_json::from_str(&json).with_context(|| format!("invalid laser profile {}", path.display()))
+}
+
+/// Reads the pen map (if any), assigns pens to this image's new colors and saves it back so the
+/// next run plots the same colors with the same pens.
+fn load_pen_map(path: Option<&Path>, vectorizer: &mut Vectorizer) -> Result<PenMap> {
+    let Some(path) = path else {
+        return Ok(PenMap::default());
+    };
+    let mut pen_map = if path.exists() {
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_str(&json)
+            .with_context(|| format!("invalid pen map {}", path.display()))?
+    } else {
+        PenMap::default()
+    };
+    pen_map.extend_from(vectorizer.document());
+    let json = serde_json::to_string_pretty(&pen_map)?;
+    fs::write(path, json + "\n").with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(pen_map)
+}
+
+fn write_debug_artifacts(vectorizer: &mut Vectorizer, dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    let artifacts = vectorizer.debug_artifacts()?;
+    for (name, bytes) in [
+        ("quantized.png", &artifacts.quantized_png),
+        ("labels.png", &artifacts.label_map_png),
+        ("overlay.png", &artifacts.overlay_png),
+    ] {
+        let path = dir.join(name);
+        fs::write(&path, bytes).with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    Ok(())
 }
 
 fn parse_mode(mode: &str) -> Result<VectorizeMode, String> {
@@ -74,14 +363,43 @@ fn parse_mode(mode: &str) -> Result<VectorizeMode, String> {
         "logo" => Ok(VectorizeMode::Logo),
         "poster" => Ok(VectorizeMode::Poster),
         "pixel" | "pixel-art" | "pixelart" => Ok(VectorizeMode::PixelArt),
-        _ => Err("mode must be one of: logo, poster, pixel".into()),
+        "auto" => Ok(VectorizeMode::Auto),
+        _ => Err("mode must be one of: logo, poster, pixel, auto".into()),
     }
 }
 
+fn parse_format(format: &str) -> Result<OutputFormat, String> {
+    format.parse()
+}
+
+fn parse_react_colors(colors: &str) -> Result<ReactColors, String> {
+    colors.parse()
+}
+
+fn parse_dpi(value: &str) -> Result<f32, String> {
+    parse_f32_range(value, "dpi", 1.0, 10000.0)
+}
+
+fn parse_chord_tolerance(value: &str) -> Result<f32, String> {
+    parse_f32_range(value, "chord-tolerance", 0.01, 10.0)
+}
+
+fn parse_hatch_spacing(value: &str) -> Result<f32, String> {
+    parse_f32_range(value, "hatch-spacing", 0.01, 100.0)
+}
+
+fn parse_hatch_angle(value: &str) -> Result<f32, String> {
+    parse_f32_range(value, "hatch-angle", -180.0, 180.0)
+}
+
 fn parse_colors(value: &str) -> Result<u8, String> {
     parse_u8_range(value, "colors", 2, 64)
 }
 
+fn parse_alpha_threshold(value: &str) -> Result<u8, String> {
+    parse_u8_range(value, "alpha-threshold", 0, 254)
+}
+
 fn parse_detail(value: &str) -> Result<f32, String> {
     parse_f32_range(value, "detail", 0.0, 1.0)
 }
@@ -127,7 +445,15 @@ fn main() {
 }
 
 fn run() -> Result<()> {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+    let timing_layer = cli.timings.then(|| {
+        let layer = TimingLayer::default();
+        let subscriber = tracing_subscriber::registry().with(layer.clone());
+        tracing::subscriber::set_global_default(subscriber).ok();
+        layer
+    });
 
     let png_bytes = fs::read(&cli.input)
         .with_context(|| format!("failed to read input file: {}", cli.input.display()))?;
@@ -138,22 +464,148 @@ fn run() -> Result<()> {
         smoothness: cli.smoothness,
         tolerance: cli.tolerance,
         mode: cli.mode,
+        alpha_threshold: cli.alpha_threshold,
+        background: cli.background.map(|background| BackgroundOptions {
+            tolerance: cli.background_tolerance,
+            keep_rect: cli.keep_background,
+            unmatte: cli.unmatte,
+            ..background
+        }),
     };
 
     if cli.debug {
         eprintln!("[open-vectorizer] options: {:?}", options);
     }
 
-    let svg = png_to_svg(&png_bytes, &options)?;
+    if cli.coco {
+        let mask = LabelMask::decode(&png_bytes)
+            .with_context(|| format!("failed to decode {}", cli.input.display()))?;
+        let coco_options = CocoOptions {
+            file_name: cli
+                .input
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            background: cli.mask_background,
+        };
+        let json = mask_to_coco(&mask, &coco_options);
+        match &cli.output {
+            Some(path) => fs::write(path, json)
+                .with_context(|| format!("failed to write {}", path.display()))?,
+            None => println!("{json}"),
+        }
+        if let Some(layer) = timing_layer {
+            eprintln!("{}", timings::format_table(&layer.stages()));
+        }
+        return Ok(());
+    }
+
+    let decode_error = || format!("failed to decode {}", cli.input.display());
+    let frames = decode_animation(&png_bytes)
+        .with_context(decode_error)?
+        .unwrap_or_default();
+    let mut vectorizer = match frames.first() {
+        Some(first) if frames.len() > 1 => Vectorizer::new(first.image.clone(), options),
+        // Stills keep their embedded palette or bit depth.
+        _ => Vectorizer::from_png_bytes(&png_bytes, options).with_context(decode_error)?,
+    };
+    if let Some(classification) = vectorizer.classification().cloned() {
+        // Tuning flags given on the command line win over the preset auto mode picked.
+        let explicit = |name: &str| matches.value_source(name) == Some(ValueSource::CommandLine);
+        let mut resolved = vectorizer.options().clone();
+        if explicit("colors") {
+            resolved.colors = cli.colors;
+        }
+        if explicit("detail") {
+            resolved.detail = cli.detail;
+        }
+        if explicit("smoothness") {
+            resolved.smoothness = cli.smoothness;
+        }
+        if explicit("tolerance") {
+            resolved.tolerance = cli.tolerance;
+        }
+        if cli.debug {
+            eprintln!("[open-vectorizer] auto mode: {classification}");
+            eprintln!("[open-vectorizer] resolved options: {:?}", resolved);
+        }
+        vectorizer.set_options(resolved);
+    }
+    let format = cli
+        .format
+        .or_else(|| cli.output.as_deref().and_then(OutputFormat::from_path))
+        .unwrap_or_default();
+    let target = OptimizeTarget {
+        max_nodes: cli.max_nodes,
+        max_paths: cli.max_paths,
+        max_bytes: cli.max_bytes,
+        max_error: cli.max_error,
+        minimize: cli.minimize.unwrap_or_default(),
+    };
+    if target != OptimizeTarget::default() {
+        // Sizes are measured in the output format. HPGL pens are assigned once the options are
+        // chosen, so the search uses a fresh pen map and leaves the pen map file alone.
+        let emitter = match format {
+            OutputFormat::Hpgl => Box::new(HpglEmitter {
+                pen_map: PenMap::default(),
+                dpi: cli.dpi,
+                flatten_tolerance: cli.chord_tolerance,
+            }),
+            _ => emitter_for(format, &cli, &mut vectorizer)?,
+        };
+        let optimized = optimize_with(&mut vectorizer, &target, emitter.as_ref());
+        let metrics = &optimized.metrics;
+        eprintln!(
+            "[open-vectorizer] optimized: colors={} mode={:?} tolerance={} -> nodes={} paths={} bytes={} error={:.2}{}",
+            optimized.options.colors,
+            optimized.options.mode,
+            optimized.options.tolerance,
+            metrics.nodes,
+            metrics.paths,
+            metrics.bytes,
+            metrics.error,
+            if optimized.met { "" } else { " (target not met; closest result)" }
+        );
+        if cli.debug {
+            eprintln!(
+                "[open-vectorizer] tried {} option sets; chosen options: {:?}",
+                optimized.evaluated, optimized.options
+            );
+        }
+    }
+    let rendered = match format {
+        // Only SVG and Lottie can animate; other formats use the first frame.
+        OutputFormat::Svg | OutputFormat::Lottie if frames.len() > 1 => {
+            let emitter = emitter_for(format, &cli, &mut vectorizer)?;
+            Pipeline::builder()
+                .emitter(emitter)
+                .build()
+                .run_bytes(&png_bytes, vectorizer.options())
+                .with_context(|| format!("failed to vectorize {}", cli.input.display()))?
+        }
+        _ => {
+            let emitter = emitter_for(format, &cli, &mut vectorizer)?;
+            vectorizer.render_with(emitter.as_ref())
+        }
+    };
+
+    if let Some(dir) = &cli.debug_dir {
+        write_debug_artifacts(&mut vectorizer, dir)?;
+    }
 
     match cli.output {
         Some(path) => {
-            fs::write(&path, svg).with_context(|| format!("failed to write {}", path.display()))?;
+            fs::write(&path, rendered)
+                .with_context(|| format!("failed to write {}", path.display()))?;
         }
         None => {
-            println!("{}", svg);
+            println!("{}", rendered);
         }
     }
 
+    if let Some(layer) = timing_layer {
+        eprintln!("{}", timings::format_table(&layer.stages()));
+    }
+
     Ok(())
 }
@@ -0,0 +1,120 @@
+use std::fmt::{self, Write as FmtWrite};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+/// One closed stage span with the counts reported by events inside it.
+#[derive(Debug, Clone)]
+pub struct StageTiming {
+    pub name: &'static str,
+    pub elapsed: Duration,
+    pub details: Vec<String>,
+}
+
+/// Records the duration of every core stage span, in the order the stages finish.
+#[derive(Debug, Clone, Default)]
+pub struct TimingLayer {
+    stages: Arc<Mutex<Vec<StageTiming>>>,
+}
+
+struct OpenStage {
+    started: Instant,
+    details: Vec<String>,
+}
+
+impl TimingLayer {
+    pub fn stages(&self) -> Vec<StageTiming> {
+        self.stages
+            .lock()
+            .map(|stages| stages.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl<S> Layer<S> for TimingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(OpenStage {
+                started: Instant::now(),
+                details: Vec::new(),
+            });
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.event_span(event) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(stage) = extensions.get_mut::<OpenStage>() {
+            event.record(&mut DetailVisitor(&mut stage.details));
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(stage) = span.extensions_mut().remove::<OpenStage>() else {
+            return;
+        };
+        if let Ok(mut stages) = self.stages.lock() {
+            stages.push(StageTiming {
+                name: span.name(),
+                elapsed: stage.started.elapsed(),
+                details: stage.details,
+            });
+        }
+    }
+}
+
+struct DetailVisitor<'a>(&'a mut Vec<String>);
+
+impl Visit for DetailVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.push(format!("{}={:?}", field.name(), value));
+    }
+}
+
+/// Renders the recorded stages as an aligned table with a total row.
+pub fn format_table(stages: &[StageTiming]) -> String {
+    let name_width = stages
+        .iter()
+        .map(|stage| stage.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("total".len());
+    let mut table = String::new();
+    writeln!(table, "{:<name_width$}  {:>10}  details", "stage", "time").ok();
+    for stage in stages {
+        writeln!(
+            table,
+            "{:<name_width$}  {:>10}  {}",
+            stage.name,
+            format_duration(stage.elapsed),
+            stage.details.join(" ")
+        )
+        .ok();
+    }
+    let total: Duration = stages.iter().map(|stage| stage.elapsed).sum();
+    write!(
+        table,
+        "{:<name_width$}  {:>10}",
+        "total",
+        format_duration(total)
+    )
+    .ok();
+    table
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.2} ms", duration.as_secs_f64() * 1000.0)
+}
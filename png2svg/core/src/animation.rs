@@ -0,0 +1,225 @@
+use std::io::Cursor;
+
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::{AnimationDecoder, DynamicImage, ImageDecoder, ImageFormat, RgbaImage};
+
+use crate::color::{load_srgb, SourceEncoding};
+use crate::{VectorDocument, VectorizeError};
+
+/// One decoded frame of an animated (or still) input, composited onto the full canvas.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub image: RgbaImage,
+    /// How long the frame is shown, in milliseconds; `0` for still images.
+    pub delay_ms: u32,
+}
+
+/// A vectorized [`Frame`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationFrame {
+    pub document: VectorDocument,
+    pub delay_ms: u32,
+}
+
+/// Decodes every frame of an animated GIF or APNG; any other image yields a single frame.
+pub fn decode_frames(bytes: &[u8]) -> Result<Vec<Frame>, VectorizeError> {
+    stage_span!("decode");
+    let frames = match read_frames(bytes)? {
+        Some(frames) => frames,
+        None => vec![Frame {
+            image: load_srgb(bytes)?.to_rgba8(),
+            delay_ms: 0,
+        }],
+    };
+    stage_event!(bytes = bytes.len(), frames = frames.len());
+    Ok(frames)
+}
+
+/// Frames of a GIF or APNG, or `None` for other images, which callers decode at their own bit
+/// depth instead of as 8-bit RGBA frames.
+pub fn decode_animation(bytes: &[u8]) -> Result<Option<Vec<Frame>>, VectorizeError> {
+    stage_span!("decode");
+    let frames = read_frames(bytes)?;
+    stage_event!(bytes = bytes.len(), frames = frames.as_ref().map_or(0, Vec::len));
+    Ok(frames)
+}
+
+fn read_frames(bytes: &[u8]) -> Result<Option<Vec<Frame>>, VectorizeError> {
+    Ok(match image::guess_format(bytes)? {
+        ImageFormat::Gif => Some(collect_frames(GifDecoder::new(Cursor::new(bytes))?)?),
+        ImageFormat::Png => {
+            let mut decoder = PngDecoder::new(Cursor::new(bytes))?;
+            if decoder.is_apng()? {
+                // Frames are color-managed like stills, so an animation matches its first frame saved alone.
+                let encoding = SourceEncoding::detect(bytes, Some(ImageFormat::Png), decoder.icc_profile()?);
+                let mut frames = collect_frames(decoder.apng()?)?;
+                if let Some(encoding) = encoding {
+                    for frame in &mut frames {
+                        let image = std::mem::take(&mut frame.image);
+                        frame.image = encoding.to_srgb(DynamicImage::ImageRgba8(image)).to_rgba8();
+                    }
+                }
+                Some(frames)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    })
+}
+
+fn collect_frames<'a>(decoder: impl AnimationDecoder<'a>) -> Result<Vec<Frame>, VectorizeError> {
+    decoder
+        .into_frames()
+        .map(|frame| {
+            let frame = frame?;
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            Ok(Frame {
+                delay_ms: numer / denom.max(1),
+                image: frame.into_buffer(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{to_hex, BackgroundOptions, Emitter, LottieEmitter, Pipeline, SvgEmitter, VectorizeMode, VectorizeOptions};
+    use image::codecs::gif::GifEncoder;
+    use image::{Delay, Rgba};
+
+    fn two_frame_gif() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut bytes);
+            for (color, delay) in [([255, 0, 0, 255], 100), ([0, 0, 255, 255], 250)] {
+                let image = RgbaImage::from_pixel(4, 3, Rgba(color));
+                let frame = image::Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(delay, 1));
+                encoder.encode_frame(frame).unwrap();
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn decodes_gif_frames_with_delays() {
+        let bytes = two_frame_gif();
+        let frames = decode_frames(&bytes).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].delay_ms, 100);
+        assert_eq!(frames[1].delay_ms, 250);
+        assert_eq!(frames[1].image.dimensions(), (4, 3));
+        assert_eq!(frames[1].image.get_pixel(0, 0).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn run_bytes_hands_animations_to_the_emitter() {
+        let options = VectorizeOptions {
+            mode: VectorizeMode::Auto,
+            ..VectorizeOptions::default()
+        };
+        let svg = crate::png_to_svg(&two_frame_gif(), &options).unwrap();
+        assert_eq!(svg.matches("@keyframes").count(), 2);
+
+        let pipeline = Pipeline::builder().emitter(LottieEmitter::default()).build();
+        let lottie: serde_json::Value = serde_json::from_str(&pipeline.run_bytes(&two_frame_gif(), &options).unwrap()).unwrap();
+        assert_eq!(lottie["layers"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn apng_frames_are_color_managed() {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_source_gamma(png::ScaledFloat::new(1.0));
+            encoder.set_animated(2, 0).unwrap();
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[128, 128, 128, 128, 128, 128]).unwrap();
+            writer.write_image_data(&[0, 0, 0, 128, 128, 128]).unwrap();
+        }
+        // Half linear light is about 188 in sRGB, as for the same image saved as a still.
+        let frames = decode_frames(&bytes).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].image.get_pixel(0, 0).0, [188, 188, 188, 255]);
+        assert_eq!(frames[1].image.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert_eq!(frames[1].image.get_pixel(1, 0).0, [188, 188, 188, 255]);
+    }
+
+    #[test]
+    fn unchanged_regions_are_written_once() {
+        // A fixed blue square and a red square that moves between two frames.
+        let frame = |red_x: u32, delay_ms| Frame {
+            image: RgbaImage::from_fn(16, 8, |x, y| match (x, y) {
+                (1..=3, 1..=3) => Rgba([0, 0, 255, 255]),
+                (x, 4..=6) if (red_x..red_x + 3).contains(&x) => Rgba([255, 0, 0, 255]),
+                _ => Rgba([255, 255, 255, 255]),
+            }),
+            delay_ms,
+        };
+        let options = VectorizeOptions::default();
+        let animation = Pipeline::default().animation(&[frame(5, 200), frame(10, 400)], &options).unwrap();
+        assert_eq!(animation.len(), 2);
+        let palette = |frame: &AnimationFrame| {
+            let mut colors: Vec<_> = frame.document.layers.iter().map(|l| (l.palette_index, l.color)).collect();
+            colors.sort();
+            colors
+        };
+        assert_eq!(palette(&animation[0]), palette(&animation[1]));
+
+        let svg = SvgEmitter.emit_frames(&animation, &options);
+        let group = |pick: fn(&[u8; 4]) -> bool| {
+            let layer = animation[0].document.layers.iter().find(|layer| pick(&layer.color)).unwrap();
+            let start = format!("<g fill=\"#{}\"", to_hex(layer.color));
+            svg.split(&start).nth(1).unwrap().split("</g>").next().unwrap().to_string()
+        };
+        let blue = group(|color| color[2] > color[0]);
+        assert_eq!(blue.matches("<path").count(), 1);
+        assert!(!blue.contains("class="));
+        let red = group(|color| color[0] > color[2]);
+        assert_eq!(red.matches("class=\"f").count(), 2);
+        assert_eq!(svg.matches("@keyframes").count(), 2);
+        assert!(svg.contains("step-end infinite"));
+        assert!(svg.contains("600ms"));
+        assert!(svg.contains("33.333%{visibility:hidden}"));
+
+        // One frame is just a static SVG.
+        assert_eq!(SvgEmitter.emit_frames(&animation[..1], &options), SvgEmitter.emit(&animation[0].document, &options));
+    }
+
+    #[test]
+    fn kept_background_is_drawn_first() {
+        let frame = |red_x: u32| Frame {
+            image: RgbaImage::from_fn(16, 8, |x, y| match (x, y) {
+                (x, 2..=5) if (red_x..red_x + 3).contains(&x) => Rgba([255, 0, 0, 255]),
+                _ => Rgba([255, 255, 255, 255]),
+            }),
+            delay_ms: 100,
+        };
+        let options = VectorizeOptions {
+            background: Some(BackgroundOptions {
+                keep_rect: true,
+                ..BackgroundOptions::default()
+            }),
+            ..VectorizeOptions::default()
+        };
+        let animation = Pipeline::default().animation(&[frame(2), frame(9)], &options).unwrap();
+        let svg = SvgEmitter.emit_frames(&animation, &options);
+        let rect = svg.find("<rect width=\"16\" height=\"8\"/>").expect("background rect");
+        assert!(rect < svg.find("<path").unwrap());
+        assert!(svg[..rect].contains("<g fill=\"#ffffff\""));
+    }
+
+    #[test]
+    fn frames_too_tall_to_stack_are_an_error() {
+        // Zero pixels wide, so the frames themselves take no memory.
+        let frame = Frame {
+            image: RgbaImage::new(0, 1 << 31),
+            delay_ms: 100,
+        };
+        let result = Pipeline::default().animation(&[frame.clone(), frame], &VectorizeOptions::default());
+        assert!(matches!(result, Err(crate::VectorizeError::Vectorize(_))));
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{QuantizedImage, Rgba16Image};
+
+/// Border pixels that must share the dominant color for it to count as the background.
+const DOMINANT_BORDER_SHARE: f64 = 0.5;
+
+/// How far (in pixels) from an edge pixel to look for the pure foreground color it was blended from.
+const UNMATTE_RADIUS: i64 = 2;
+
+/// Which color to treat as background, and what to do with it.
+///
+/// Matching pixels become transparent before quantization, so the background neither takes a
+/// palette slot nor turns into one huge path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackgroundOptions {
+    /// Background color; `None` detects the dominant color along the image border.
+    pub color: Option<[u8; 3]>,
+    /// Largest per-channel difference (0-255) from the background color that still matches,
+    /// e.g. to absorb JPEG noise.
+    pub tolerance: u8,
+    /// Emit the removed background as one full-size rectangle underneath everything else.
+    pub keep_rect: bool,
+    /// Recover foreground color and alpha of edge pixels that were blended with the background,
+    /// so removing it leaves no halo.
+    pub unmatte: bool,
+}
+
+impl Default for BackgroundOptions {
+    fn default() -> Self {
+        Self {
+            color: None,
+            tolerance: 16,
+            keep_rect: false,
+            unmatte: false,
+        }
+    }
+}
+
+impl FromStr for BackgroundOptions {
+    type Err = String;
+
+    /// `auto`, or an `rrggbb` hex color with an optional leading `#`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.eq_ignore_ascii_case("auto") {
+            return Ok(Self::default());
+        }
+        let hex = value.trim_start_matches('#');
+        let channel = |range: std::ops::Range<usize>| u8::from_str_radix(hex.get(range)?, 16).ok();
+        match (hex.len(), channel(0..2), channel(2..4), channel(4..6)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Self {
+                color: Some([r, g, b]),
+                ..Self::default()
+            }),
+            _ => Err(format!("background must be `auto` or a hex color like `ffffff`, got {value:?}")),
+        }
+    }
+}
+
+/// Makes background pixels transparent in place and returns the background color, or `None`
+/// if auto-detection found no dominant border color. Pixels at or below `alpha_cutoff` are
+/// already transparent and are ignored.
+pub(crate) fn remove_background(image: &mut Rgba16Image, options: &BackgroundOptions, alpha_cutoff: u16) -> Option<[u8; 4]> {
+    stage_span!("background");
+    let color = match options.color {
+        Some([r, g, b]) => [r, g, b, 255],
+        None => dominant_color(border(image.width(), image.height()).map(|(x, y)| narrow(image.get_pixel(x, y).0)), alpha_cutoff)?,
+    };
+    if options.unmatte {
+        unmatte(image, color, options.tolerance, alpha_cutoff);
+    }
+    for pixel in image.pixels_mut() {
+        if pixel[3] > alpha_cutoff && matches(narrow(pixel.0), color, options.tolerance) {
+            pixel.0 = [0; 4];
+        }
+    }
+    stage_event!(color = ?color);
+    Some(color)
+}
+
+/// Like [`remove_background`] for an already indexed image: matching palette entries become
+/// transparent.
+pub(crate) fn remove_palette_background(quantized: &mut QuantizedImage, options: &BackgroundOptions) -> Option<[u8; 4]> {
+    let color = match options.color {
+        Some([r, g, b]) => [r, g, b, 255],
+        None => {
+            let width = quantized.width as usize;
+            let border = border(quantized.width, quantized.height)
+                .map(|(x, y)| quantized.palette[quantized.indices[y as usize * width + x as usize]]);
+            dominant_color(border, 0)?
+        }
+    };
+    for entry in &mut quantized.palette {
+        if entry[3] > 0 && matches(*entry, color, options.tolerance) {
+            *entry = [0, 0, 0, 0];
+        }
+    }
+    Some(color)
+}
+
+/// Un-blends pixels next to the background: each is assumed to be `alpha * F + (1 - alpha) * B`
+/// for background `B` and the nearby pixel `F` that differs most from `B`, and is replaced by
+/// `F` with that alpha. Interior pixels, which touch no background, are left alone.
+fn unmatte(image: &mut Rgba16Image, background: [u8; 4], tolerance: u8, alpha_cutoff: u16) {
+    let (width, height) = (i64::from(image.width()), i64::from(image.height()));
+    let source = image.clone();
+    let is_background: Vec<bool> = source
+        .pixels()
+        .map(|p| p[3] > alpha_cutoff && matches(narrow(p.0), background, tolerance))
+        .collect();
+    let rgb = |p: [u16; 4]| [p[0], p[1], p[2]].map(|value| f32::from(value) / 65535.0);
+    let b = [background[0], background[1], background[2]].map(|value| f32::from(value) / 255.0);
+    let offset = |c: [f32; 3]| [c[0] - b[0], c[1] - b[1], c[2] - b[2]];
+    let dot = |u: [f32; 3], v: [f32; 3]| u[0] * v[0] + u[1] * v[1] + u[2] * v[2];
+    let neighbors = |x: i64, y: i64, radius: i64| {
+        (-radius..=radius)
+            .flat_map(move |dy| (-radius..=radius).map(move |dx| (x + dx, y + dy)))
+            .filter(move |&(nx, ny)| (0..width).contains(&nx) && (0..height).contains(&ny))
+            .map(move |(nx, ny)| (ny * width + nx) as usize)
+    };
+
+    for (i, pixel) in image.pixels_mut().enumerate() {
+        let (x, y) = (i as i64 % width, i as i64 / width);
+        if is_background[i] || pixel[3] <= alpha_cutoff || !neighbors(x, y, 1).any(|n| is_background[n]) {
+            continue;
+        }
+        let foreground = neighbors(x, y, UNMATTE_RADIUS)
+            .filter(|&n| !is_background[n])
+            .map(|n| source.as_raw()[n * 4..n * 4 + 4].try_into().expect("four channels"))
+            .filter(|p: &[u16; 4]| p[3] > alpha_cutoff)
+            .max_by(|&p, &q| {
+                let (p, q) = (offset(rgb(p)), offset(rgb(q)));
+                dot(p, p).total_cmp(&dot(q, q))
+            });
+        let Some(foreground) = foreground else {
+            continue;
+        };
+        let f = offset(rgb(foreground));
+        let spread = dot(f, f);
+        if spread < 1e-6 {
+            continue;
+        }
+        let alpha = (dot(offset(rgb(pixel.0)), f) / spread).clamp(0.0, 1.0);
+        pixel.0 = [
+            foreground[0],
+            foreground[1],
+            foreground[2],
+            (f32::from(pixel[3]) * alpha).round() as u16,
+        ];
+    }
+}
+
+/// Coordinates of every pixel on the image border, each once.
+fn border(width: u32, height: u32) -> impl Iterator<Item = (u32, u32)> {
+    (0..height).flat_map(move |y| {
+        let edge_row = y == 0 || y + 1 == height;
+        let step = if edge_row { 1 } else { width.saturating_sub(1).max(1) };
+        (0..width).step_by(step as usize).map(move |x| (x, y))
+    })
+}
+
+/// Mean of the most common visible border color (bucketed to 5 bits per channel), if it covers
+/// at least [`DOMINANT_BORDER_SHARE`] of the border.
+fn dominant_color(border: impl Iterator<Item = [u8; 4]>, alpha_cutoff: u16) -> Option<[u8; 4]> {
+    let mut buckets: HashMap<[u8; 3], (usize, [u64; 3])> = HashMap::new();
+    let mut total = 0usize;
+    for [r, g, b, a] in border {
+        total += 1;
+        if u16::from(a) * 257 <= alpha_cutoff {
+            continue;
+        }
+        let (count, sums) = buckets.entry([r >> 3, g >> 3, b >> 3]).or_default();
+        *count += 1;
+        for (sum, channel) in sums.iter_mut().zip([r, g, b]) {
+            *sum += u64::from(channel);
+        }
+    }
+    let (count, sums) = buckets.into_values().max_by_key(|&(count, _)| count)?;
+    if (count as f64) < total as f64 * DOMINANT_BORDER_SHARE {
+        return None;
+    }
+    let [r, g, b] = sums.map(|sum| ((sum + count as u64 / 2) / count as u64) as u8);
+    Some([r, g, b, 255])
+}
+
+fn matches(color: [u8; 4], background: [u8; 4], tolerance: u8) -> bool {
+    color[..3].iter().zip(&background[..3]).all(|(&a, &b)| a.abs_diff(b) <= tolerance)
+}
+
+fn narrow(pixel: [u16; 4]) -> [u8; 4] {
+    pixel.map(|value| ((u32::from(value) * 255 + 32767) / 65535) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn detects_and_removes_a_noisy_white_background() {
+        let mut image = Rgba16Image::from_fn(10, 10, |x, y| match (x, y) {
+            (3..=6, 3..=6) => Rgba([0, 0, 40000, 65535]),
+            // JPEG-like noise around white.
+            _ => Rgba([65535 - (x + y) as u16 * 100, 65535, 65535 - x as u16 * 150, 65535]),
+        });
+        let color = remove_background(&mut image, &BackgroundOptions::default(), 0).unwrap();
+        assert!(color[..3].iter().all(|&channel| channel > 240));
+        assert_eq!(image.pixels().filter(|p| p[3] == 0).count(), 84);
+
+        let busy = Rgba16Image::from_fn(4, 4, |x, y| Rgba([x as u16 * 20000, y as u16 * 20000, 0, 65535]));
+        assert_eq!(remove_background(&mut busy.clone(), &BackgroundOptions::default(), 0), None);
+    }
+
+    #[test]
+    fn unmatte_recovers_edge_color_and_alpha() {
+        // A red bar on green whose edge column is a 25% blend of the two.
+        let (red, green) = ([60000u16, 0, 0], [0u16, 65535, 0]);
+        let mut image = Rgba16Image::from_fn(7, 3, |x, _| match x {
+            0..=2 => Rgba([red[0], red[1], red[2], 65535]),
+            3 => Rgba([15000, 49151, 0, 65535]),
+            _ => Rgba([green[0], green[1], green[2], 65535]),
+        });
+        let options = BackgroundOptions {
+            color: Some([0, 255, 0]),
+            unmatte: true,
+            ..BackgroundOptions::default()
+        };
+        remove_background(&mut image, &options, 0);
+        let edge = image.get_pixel(3, 1).0;
+        assert_eq!(edge[..3], red);
+        assert!((i32::from(edge[3]) - 16384).abs() < 100, "alpha {}", edge[3]);
+        assert_eq!(image.get_pixel(1, 1).0, [60000, 0, 0, 65535]);
+        assert_eq!(image.get_pixel(5, 1)[3], 0);
+    }
+
+    #[test]
+    fn parses_auto_or_hex() {
+        assert_eq!("auto".parse::<BackgroundOptions>().unwrap().color, None);
+        assert_eq!("#10ff00".parse::<BackgroundOptions>().unwrap().color, Some([16, 255, 0]));
+        assert!("white".parse::<BackgroundOptions>().is_err());
+    }
+}
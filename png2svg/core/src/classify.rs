@@ -0,0 +1,328 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use image::RgbaImage;
+use serde::Serialize;
+
+use crate::{color_distance, VectorizeMode, VectorizeOptions};
+
+/// Neighbours closer than this (squared RGBA distance) count as the same flat color.
+const FLAT_DISTANCE: u32 = 12 * 12;
+/// Neighbours further apart than this (squared RGBA distance) form an edge.
+const EDGE_DISTANCE: u32 = 48 * 48;
+/// Distinct colors counted before giving up; more than this is a photo-like image either way.
+const MAX_COUNTED_COLORS: usize = 4096;
+
+/// What kind of artwork an image looks like, as far as picking vectorization settings goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImageKind {
+    Logo,
+    Poster,
+    PixelArt,
+    LineArt,
+}
+
+impl fmt::Display for ImageKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ImageKind::Logo => "logo",
+            ImageKind::Poster => "poster",
+            ImageKind::PixelArt => "pixel art",
+            ImageKind::LineArt => "line art",
+        })
+    }
+}
+
+/// Measurements [`classify_image`] bases its decision on.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImageMetrics {
+    /// Distinct RGBA colors, capped at 4097.
+    pub unique_colors: usize,
+    /// Share of pixels that match all four neighbours: high for flat artwork, low for photos.
+    pub flat_share: f32,
+    /// Share of color edges that are a single hard step rather than an anti-aliased ramp.
+    pub edge_sharpness: f32,
+    /// Common divisor of all color run lengths; above 1 for upscaled pixel art.
+    pub block_size: u32,
+    /// Share of pixels that differ from the most common (background) color.
+    pub coverage: f32,
+    /// Median thickness, in pixels, of the non-background strokes and shapes.
+    pub stroke_width: f32,
+    /// Interquartile range of the thickness over its median; low when strokes are uniform.
+    pub stroke_variation: f32,
+}
+
+/// The detected [`ImageKind`], with the metrics and reasons behind it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Classification {
+    pub kind: ImageKind,
+    pub metrics: ImageMetrics,
+    /// Human-readable reasons for the decision, e.g. `"2 colors cover 97% of the image"`.
+    pub reasons: Vec<String>,
+}
+
+impl Classification {
+    pub fn mode(&self) -> VectorizeMode {
+        match self.kind {
+            ImageKind::Logo | ImageKind::LineArt => VectorizeMode::Logo,
+            ImageKind::Poster => VectorizeMode::Poster,
+            ImageKind::PixelArt => VectorizeMode::PixelArt,
+        }
+    }
+
+    /// `options` with the mode and the tuning options (`colors`, `detail`, `smoothness`,
+    /// `tolerance`) set for the detected kind. Alpha and background settings are kept.
+    pub fn apply(&self, options: &VectorizeOptions) -> VectorizeOptions {
+        let (colors, detail, smoothness, tolerance) = match self.kind {
+            ImageKind::Logo => (8, 0.6, 0.6, 1.5),
+            ImageKind::LineArt => (2, 1.0, 0.7, 1.0),
+            ImageKind::Poster => (16, 0.8, 0.5, 1.5),
+            ImageKind::PixelArt => (self.metrics.unique_colors.clamp(2, 64) as u8, 1.0, 0.0, 0.25),
+        };
+        VectorizeOptions {
+            colors,
+            detail,
+            smoothness,
+            tolerance,
+            mode: self.mode(),
+            ..options.clone()
+        }
+    }
+}
+
+impl fmt::Display for Classification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind, self.reasons.join("; "))
+    }
+}
+
+/// Guesses what kind of artwork `image` is from its color count, edge sharpness, pixel
+/// block size and stroke thickness.
+pub fn classify_image(image: &RgbaImage) -> Classification {
+    stage_span!("classify");
+    let metrics = measure(image);
+    let (width, height) = image.dimensions();
+    let percent = |share: f32| format!("{:.0}%", share * 100.0);
+    let thin_stroke = (width.min(height) as f32 / 40.0).max(4.0);
+
+    let mut reasons = Vec::new();
+    let kind = if metrics.edge_sharpness >= 0.9
+        && metrics.unique_colors <= 256
+        && (metrics.block_size >= 2 || width.max(height) <= 128)
+    {
+        reasons.push(format!("{} hard edges without anti-aliasing", percent(metrics.edge_sharpness)));
+        reasons.push(if metrics.block_size >= 2 {
+            format!("drawn in {0}x{0} pixel blocks", metrics.block_size)
+        } else {
+            format!("small ({width}x{height}) with {} colors", metrics.unique_colors)
+        });
+        ImageKind::PixelArt
+    } else if metrics.flat_share < 0.6 {
+        reasons.push(format!("only {} of pixels sit in flat color areas", percent(metrics.flat_share)));
+        reasons.push(format!("{} colors", color_count(metrics.unique_colors)));
+        ImageKind::Poster
+    } else if metrics.coverage <= 0.35 && metrics.stroke_width <= thin_stroke && metrics.stroke_variation <= 0.75 {
+        reasons.push(format!("strokes cover {} of the image", percent(metrics.coverage)));
+        reasons.push(format!(
+            "they are thin ({:.1} px median) and even (variation {:.2})",
+            metrics.stroke_width, metrics.stroke_variation
+        ));
+        ImageKind::LineArt
+    } else {
+        reasons.push(format!("{} of pixels sit in flat color areas", percent(metrics.flat_share)));
+        reasons.push(format!("{} colors", color_count(metrics.unique_colors)));
+        ImageKind::Logo
+    };
+    stage_event!(kind = %kind);
+    Classification { kind, metrics, reasons }
+}
+
+/// `options` with [`VectorizeMode::Auto`] resolved for `image`; other modes are returned as is.
+pub(crate) fn resolve_auto(image: &RgbaImage, options: &VectorizeOptions) -> VectorizeOptions {
+    match options.mode {
+        VectorizeMode::Auto => classify_image(image).apply(options),
+        _ => options.clone(),
+    }
+}
+
+fn color_count(count: usize) -> String {
+    if count > MAX_COUNTED_COLORS {
+        format!("over {MAX_COUNTED_COLORS}")
+    } else {
+        count.to_string()
+    }
+}
+
+fn measure(image: &RgbaImage) -> ImageMetrics {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    // All fully transparent pixels are one color, whatever their RGB.
+    let pixels: Vec<[u8; 4]> = image.pixels().map(|p| if p[3] == 0 { [0; 4] } else { p.0 }).collect();
+    let at = |x: usize, y: usize| pixels[y * width + x];
+    let flat = |a: [u8; 4], b: [u8; 4]| color_distance(a, b) <= FLAT_DISTANCE;
+
+    let mut unique = HashSet::new();
+    for &pixel in &pixels {
+        if unique.len() > MAX_COUNTED_COLORS {
+            break;
+        }
+        unique.insert(pixel);
+    }
+
+    let mut flat_pixels = 0usize;
+    for y in 0..height {
+        for x in 0..width {
+            let p = at(x, y);
+            let neighbours = [
+                (x > 0).then(|| at(x - 1, y)),
+                (x + 1 < width).then(|| at(x + 1, y)),
+                (y > 0).then(|| at(x, y - 1)),
+                (y + 1 < height).then(|| at(x, y + 1)),
+            ];
+            if neighbours.into_iter().flatten().all(|n| flat(p, n)) {
+                flat_pixels += 1;
+            }
+        }
+    }
+
+    // Edges along rows and columns: an edge is soft when a pixel next to the step is a blend
+    // of its own neighbours, as anti-aliasing produces.
+    let blend = |a: [u8; 4], m: [u8; 4], b: [u8; 4]| {
+        let d = |u, v| (color_distance(u, v) as f32).sqrt();
+        !flat(a, m) && !flat(m, b) && d(a, m) + d(m, b) <= d(a, b) * 1.1
+    };
+    let lines = (0..height)
+        .map(|y| (0..width).map(|x| at(x, y)).collect::<Vec<_>>())
+        .chain((0..width).map(|x| (0..height).map(|y| at(x, y)).collect()));
+    let (mut edges, mut hard_edges, mut block_size) = (0usize, 0usize, 0usize);
+    for line in lines {
+        for i in 0..line.len().saturating_sub(1) {
+            if color_distance(line[i], line[i + 1]) > EDGE_DISTANCE {
+                edges += 1;
+                let soft_before = i > 0 && blend(line[i - 1], line[i], line[i + 1]);
+                let soft_after = i + 2 < line.len() && blend(line[i], line[i + 1], line[i + 2]);
+                if !soft_before && !soft_after {
+                    hard_edges += 1;
+                }
+            }
+        }
+        // Runs touching the image border may be cut off, so only inner runs count.
+        let runs = runs(&line, |a, b| a == b);
+        for &(start, len) in &runs {
+            if start > 0 && start + len < line.len() {
+                block_size = gcd(block_size, len);
+            }
+        }
+    }
+
+    let background = {
+        let mut counts: HashMap<[u8; 4], usize> = HashMap::new();
+        for &pixel in &pixels {
+            *counts.entry(pixel).or_default() += 1;
+        }
+        counts.into_iter().max_by_key(|&(_, count)| count).map(|(color, _)| color).unwrap_or_default()
+    };
+    let foreground: Vec<bool> = pixels.iter().map(|&p| !flat(p, background)).collect();
+    let covered = foreground.iter().filter(|&&f| f).count();
+
+    // Thickness at a foreground pixel: the shorter of its horizontal and vertical runs.
+    let mut thickness = vec![usize::MAX; pixels.len()];
+    for y in 0..height {
+        let row: Vec<bool> = (0..width).map(|x| foreground[y * width + x]).collect();
+        for (start, len) in runs(&row, |a, b| a == b) {
+            if row[start] {
+                (start..start + len).for_each(|x| thickness[y * width + x] = len);
+            }
+        }
+    }
+    for x in 0..width {
+        let column: Vec<bool> = (0..height).map(|y| foreground[y * width + x]).collect();
+        for (start, len) in runs(&column, |a, b| a == b) {
+            if column[start] {
+                (start..start + len).for_each(|y| thickness[y * width + x] = thickness[y * width + x].min(len));
+            }
+        }
+    }
+    let mut strokes: Vec<f32> = thickness.into_iter().filter(|&t| t != usize::MAX).map(|t| t as f32).collect();
+    strokes.sort_by(f32::total_cmp);
+    // Quartiles rather than a standard deviation: stroke corners measure as long runs.
+    let quantile = |q: usize| strokes.get(strokes.len() * q / 4).copied().unwrap_or(0.0);
+    let stroke_width = quantile(2);
+    let stroke_variation = if stroke_width > 0.0 { (quantile(3) - quantile(1)) / stroke_width } else { 0.0 };
+
+    let total = pixels.len().max(1) as f32;
+    ImageMetrics {
+        unique_colors: unique.len(),
+        flat_share: flat_pixels as f32 / total,
+        edge_sharpness: if edges == 0 { 1.0 } else { hard_edges as f32 / edges as f32 },
+        block_size: block_size.max(1) as u32,
+        coverage: covered as f32 / total,
+        stroke_width,
+        stroke_variation,
+    }
+}
+
+/// `(start, length)` of each run of equal neighbours.
+fn runs<T: Copy>(line: &[T], same: impl Fn(T, T) -> bool) -> Vec<(usize, usize)> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    for i in 1..=line.len() {
+        if i == line.len() || !same(line[i - 1], line[i]) {
+            runs.push((start, i - start));
+            start = i;
+        }
+    }
+    runs
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn recognizes_each_kind() {
+        // 4x upscaled checker sprite.
+        let sprite = RgbaImage::from_fn(64, 64, |x, y| {
+            if (x / 4 + y / 4) % 3 == 0 { Rgba([255, 200, 0, 255]) } else { Rgba([40, 40, 120, 255]) }
+        });
+        let classification = classify_image(&sprite);
+        assert_eq!(classification.kind, ImageKind::PixelArt);
+        assert_eq!(classification.metrics.block_size, 4);
+        assert_eq!(classification.mode(), VectorizeMode::PixelArt);
+
+        // Noise-like gradient.
+        let photo = RgbaImage::from_fn(64, 64, |x, y| Rgba([(x * 4) as u8, (y * 4) as u8, ((x * y) % 251) as u8, 255]));
+        assert_eq!(classify_image(&photo).kind, ImageKind::Poster);
+
+        // Thin dark outlines of two boxes on white.
+        let drawing = RgbaImage::from_fn(200, 200, |x, y| {
+            let on_box = |x0: u32, x1: u32| {
+                (x0..=x1).contains(&x) && (x0..=x1).contains(&y) && (x - x0 < 2 || x1 - x < 2 || y - x0 < 2 || x1 - y < 2)
+            };
+            if on_box(20, 90) || on_box(100, 180) { Rgba([20, 20, 20, 255]) } else { Rgba([255, 255, 255, 255]) }
+        });
+        let classification = classify_image(&drawing);
+        assert_eq!(classification.kind, ImageKind::LineArt, "{classification}");
+        assert_eq!(classification.apply(&VectorizeOptions::default()).colors, 2);
+
+        // Solid shapes with soft edges.
+        let logo = RgbaImage::from_fn(200, 200, |x, y| {
+            let d = ((x as f32 - 100.0).powi(2) + (y as f32 - 100.0).powi(2)).sqrt();
+            let t = (d - 60.0).clamp(0.0, 1.0);
+            let mix = |a: f32, b: f32| (a * (1.0 - t) + b * t) as u8;
+            Rgba([mix(200.0, 255.0), mix(30.0, 255.0), mix(30.0, 255.0), 255])
+        });
+        let classification = classify_image(&logo);
+        assert_eq!(classification.kind, ImageKind::Logo, "{classification}");
+        assert!(!classification.reasons.is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+use std::io::Cursor;
+use std::sync::OnceLock;
+
+use image::{DynamicImage, ImageBuffer, ImageDecoder, ImageFormat, ImageReader, LumaA};
+use moxcms::{ColorProfile, DataColorSpace, Layout, TransformOptions};
+
+use crate::{Rgba16Image, VectorizeError};
+
+/// PNG `gAMA` values this close to 1/2.2 are treated as sRGB, as browsers do.
+const SRGB_GAMMA_TOLERANCE: f32 = 0.01;
+
+/// How an image's samples relate to sRGB, per its embedded metadata.
+pub(crate) enum SourceEncoding {
+    Icc(Vec<u8>),
+    /// PNG `gAMA`: samples are linear light raised to this power.
+    Gamma(f32),
+}
+
+impl SourceEncoding {
+    /// The encoding of `bytes`, given the ICC profile its decoder reported; `None` for sRGB or
+    /// untagged images, which need no conversion.
+    pub(crate) fn detect(bytes: &[u8], format: Option<ImageFormat>, icc: Option<Vec<u8>>) -> Option<Self> {
+        match icc {
+            Some(icc) => Some(SourceEncoding::Icc(icc)),
+            None if format == Some(ImageFormat::Png) => png_gamma(bytes).map(SourceEncoding::Gamma),
+            None => None,
+        }
+    }
+
+    /// Converts to sRGB as 16-bit RGBA; an unusable ICC profile leaves the image as it is.
+    pub(crate) fn to_srgb(&self, image: DynamicImage) -> DynamicImage {
+        match self {
+            SourceEncoding::Icc(icc) => from_icc(&image, icc).unwrap_or(image),
+            SourceEncoding::Gamma(gamma) => from_gamma(&image, *gamma),
+        }
+    }
+}
+
+/// Decodes `bytes` and converts it to sRGB if it carries an ICC profile or (for PNGs without
+/// `sRGB`/`iCCP`) a non-sRGB `gAMA` chunk. Converted images come back as 16-bit RGBA so the
+/// conversion does not add rounding before quantization.
+pub(crate) fn load_srgb(bytes: &[u8]) -> Result<DynamicImage, VectorizeError> {
+    let reader = ImageReader::new(Cursor::new(bytes)).with_guessed_format().map_err(image::ImageError::IoError)?;
+    let format = reader.format();
+    let mut decoder = reader.into_decoder()?;
+    let icc = decoder.icc_profile()?;
+    let image = DynamicImage::from_decoder(decoder)?;
+
+    Ok(match SourceEncoding::detect(bytes, format, icc) {
+        Some(encoding) => encoding.to_srgb(image),
+        None => image,
+    })
+}
+
+/// The `gAMA` value of a PNG that has neither `sRGB` nor `iCCP`, unless it is (close to) sRGB.
+fn png_gamma(bytes: &[u8]) -> Option<f32> {
+    let reader = png::Decoder::new(Cursor::new(bytes)).read_info().ok()?;
+    let info = reader.info();
+    if info.srgb.is_some() || info.icc_profile.is_some() {
+        return None;
+    }
+    let gamma = info.gama_chunk?.into_scaled() as f32 / 100_000.0;
+    (gamma > 0.0 && (gamma - 1.0 / 2.2).abs() > SRGB_GAMMA_TOLERANCE).then_some(gamma)
+}
+
+fn from_gamma(image: &DynamicImage, gamma: f32) -> DynamicImage {
+    let lut: Vec<u16> = (0..=u16::MAX)
+        .map(|value| {
+            let linear = (f32::from(value) / 65535.0).powf(1.0 / gamma);
+            (linear_to_srgb(linear) * 65535.0).round() as u16
+        })
+        .collect();
+    let mut rgba = image.to_rgba16();
+    for pixel in rgba.pixels_mut() {
+        for channel in &mut pixel.0[..3] {
+            *channel = lut[usize::from(*channel)];
+        }
+    }
+    DynamicImage::ImageRgba16(rgba)
+}
+
+/// Converts through the embedded profile; `None` if it is unusable or not RGB/gray.
+fn from_icc(image: &DynamicImage, icc: &[u8]) -> Option<DynamicImage> {
+    let source = ColorProfile::new_from_slice(icc).ok()?;
+    let (layout, samples): (Layout, Vec<u16>) = match source.color_space {
+        DataColorSpace::Rgb => (Layout::Rgba, image.to_rgba16().into_raw()),
+        DataColorSpace::Gray => {
+            let gray: ImageBuffer<LumaA<u16>, Vec<u16>> = image.to_luma_alpha16();
+            (Layout::GrayAlpha, gray.into_raw())
+        }
+        _ => return None,
+    };
+    let transform = source
+        .create_transform_16bit(layout, &ColorProfile::new_srgb(), Layout::Rgba, TransformOptions::default())
+        .ok()?;
+    let mut srgb = vec![0u16; image.width() as usize * image.height() as usize * 4];
+    transform.transform(&samples, &mut srgb).ok()?;
+    Rgba16Image::from_raw(image.width(), image.height(), srgb).map(DynamicImage::ImageRgba16)
+}
+
+/// Linear-light value (`0.0..=1.0`) of a 16-bit sRGB-encoded sample.
+pub(crate) fn srgb_to_linear16(value: u16) -> f32 {
+    static LUT: OnceLock<Vec<f32>> = OnceLock::new();
+    let lut = LUT.get_or_init(|| (0..=u16::MAX).map(|v| srgb_to_linear(f32::from(v) / 65535.0)).collect());
+    lut[usize::from(value)]
+}
+
+/// 16-bit sRGB encoding of a linear-light value.
+pub(crate) fn linear_to_srgb16(linear: f32) -> u16 {
+    (linear_to_srgb(linear) * 65535.0).round() as u16
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(linear: f32) -> f32 {
+    let linear = linear.clamp(0.0, 1.0);
+    if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray_png(gamma: Option<f32>) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, 1, 1);
+            encoder.set_color(png::ColorType::Rgb);
+            if let Some(gamma) = gamma {
+                encoder.set_source_gamma(png::ScaledFloat::new(gamma));
+            }
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[128, 128, 128]).unwrap();
+        }
+        bytes
+    }
+
+    #[test]
+    fn linear_gamma_png_is_converted_to_srgb() {
+        // Half linear light is about 188 in sRGB.
+        let image = load_srgb(&gray_png(Some(1.0))).unwrap();
+        let [r, g, b, a] = image.to_rgba8().get_pixel(0, 0).0;
+        assert_eq!((r, g, b, a), (188, 188, 188, 255));
+
+        // Untagged and 1/2.2-tagged images are left alone.
+        assert_eq!(load_srgb(&gray_png(None)).unwrap().to_rgba8().get_pixel(0, 0).0, [128, 128, 128, 255]);
+        assert_eq!(load_srgb(&gray_png(Some(0.45455))).unwrap().to_rgba8().get_pixel(0, 0).0, [128, 128, 128, 255]);
+    }
+
+    #[test]
+    fn icc_profile_is_applied() {
+        let mut info = png::Info::with_size(1, 1);
+        info.color_type = png::ColorType::Rgb;
+        info.icc_profile = Some(ColorProfile::new_display_p3().encode().unwrap().into());
+        let mut bytes = Vec::new();
+        {
+            let mut writer = png::Encoder::with_info(&mut bytes, info).unwrap().write_header().unwrap();
+            writer.write_image_data(&[200, 100, 50]).unwrap();
+        }
+        // The same numbers mean a more saturated orange in Display P3.
+        let image = load_srgb(&bytes).unwrap();
+        assert!(matches!(image, DynamicImage::ImageRgba16(_)));
+        let [r, g, b, _] = image.to_rgba8().get_pixel(0, 0).0;
+        assert!(r > 210 && g < 100 && b < 50, "got {r} {g} {b}");
+    }
+
+    #[test]
+    fn transfer_functions_round_trip() {
+        for value in [0, 1, 1000, 32768, 65535] {
+            assert_eq!(linear_to_srgb16(srgb_to_linear16(value)), value);
+        }
+        assert!((srgb_to_linear16(u16::MAX / 2) - 0.214).abs() < 0.001);
+    }
+}
@@ -0,0 +1,173 @@
+use image::codecs::png::PngEncoder;
+use image::{ImageEncoder, Rgba, RgbaImage};
+
+use crate::{PathCommand, Point, QuantizedImage, Region, TracedRegion, VectorDocument, VectorizeError};
+
+/// PNG-encoded snapshots of the intermediate stages, for diagnosing bad output.
+#[derive(Debug, Clone)]
+pub struct DebugArtifacts {
+    /// Every pixel replaced by its palette color.
+    pub quantized_png: Vec<u8>,
+    /// One false color per connected region; unlabeled (transparent) pixels are black.
+    pub label_map_png: Vec<u8>,
+    /// Source image, upscaled, with raw contours in red and simplified vertices in cyan.
+    pub overlay_png: Vec<u8>,
+}
+
+impl DebugArtifacts {
+    pub fn new(
+        source: &RgbaImage,
+        quantized: &QuantizedImage,
+        regions: &[Region],
+        traced: &[TracedRegion],
+        document: &VectorDocument,
+    ) -> Result<Self, VectorizeError> {
+        Ok(Self {
+            quantized_png: encode_png(&quantized_image(quantized))?,
+            label_map_png: encode_png(&label_map(quantized, regions))?,
+            overlay_png: encode_png(&overlay(source, traced, document))?,
+        })
+    }
+}
+
+const CONTOUR_COLOR: Rgba<u8> = Rgba([255, 48, 48, 255]);
+const VERTEX_COLOR: Rgba<u8> = Rgba([0, 230, 255, 255]);
+const OVERLAY_MAX_SIDE: u32 = 1024;
+const OVERLAY_MAX_SCALE: u32 = 8;
+
+pub(crate) fn quantized_image(quantized: &QuantizedImage) -> RgbaImage {
+    RgbaImage::from_fn(quantized.width, quantized.height, |x, y| {
+        let idx = quantized.indices[(y * quantized.width + x) as usize];
+        Rgba(quantized.palette[idx])
+    })
+}
+
+fn label_map(quantized: &QuantizedImage, regions: &[Region]) -> RgbaImage {
+    let mut map = RgbaImage::from_pixel(quantized.width, quantized.height, Rgba([0, 0, 0, 255]));
+    for (label, region) in regions.iter().enumerate() {
+        let color = false_color(label);
+        for &(x, y) in &region.pixels {
+            map.put_pixel(x as u32, y as u32, color);
+        }
+    }
+    map
+}
+
+// Golden-angle hue steps keep neighbouring labels visually distinct.
+fn false_color(label: usize) -> Rgba<u8> {
+    let hue = (label as f32 * 137.508) % 360.0;
+    let sector = hue / 60.0;
+    let x = 1.0 - (sector % 2.0 - 1.0).abs();
+    let (r, g, b) = match sector as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    let channel = |v: f32| (55.0 + v * 200.0) as u8;
+    Rgba([channel(r), channel(g), channel(b), 255])
+}
+
+fn overlay(source: &RgbaImage, traced: &[TracedRegion], document: &VectorDocument) -> RgbaImage {
+    let longest = source.width().max(source.height()).max(1);
+    let scale = (OVERLAY_MAX_SIDE / longest).clamp(1, OVERLAY_MAX_SCALE);
+
+    // Dim the source so the strokes stand out; transparent areas become a dark backdrop.
+    let mut canvas = RgbaImage::from_fn(source.width() * scale, source.height() * scale, |x, y| {
+        let [r, g, b, a] = source.get_pixel(x / scale, y / scale).0;
+        let blend = |c: u8| ((c as u32 * a as u32 / 255) * 3 / 5 + 24) as u8;
+        Rgba([blend(r), blend(g), blend(b), 255])
+    });
+
+    let to_canvas = |p: Point| (p.x * scale as f32, p.y * scale as f32);
+
+    for region in traced {
+        for outline in std::iter::once(&region.outline).chain(&region.holes) {
+            for pair in outline.windows(2) {
+                draw_line(&mut canvas, to_canvas(pair[0]), to_canvas(pair[1]), CONTOUR_COLOR);
+            }
+        }
+    }
+
+    let radius = (scale as i32 / 3).max(1);
+    for layer in &document.layers {
+        for path in &layer.paths {
+            for command in &path.commands {
+                let vertex = match *command {
+                    PathCommand::MoveTo(p) | PathCommand::LineTo(p) => p,
+                    PathCommand::CubicTo { to, .. } => to,
+                    PathCommand::Close => continue,
+                };
+                let (cx, cy) = to_canvas(vertex);
+                draw_square(&mut canvas, cx.round() as i32, cy.round() as i32, radius, VERTEX_COLOR);
+            }
+        }
+    }
+
+    canvas
+}
+
+fn draw_line(canvas: &mut RgbaImage, from: (f32, f32), to: (f32, f32), color: Rgba<u8>) {
+    let steps = (to.0 - from.0).abs().max((to.1 - from.1).abs()).ceil().max(1.0) as u32;
+    for step in 0..=steps {
+        let t = step as f32 / steps as f32;
+        let x = from.0 + (to.0 - from.0) * t;
+        let y = from.1 + (to.1 - from.1) * t;
+        put_checked(canvas, x.round() as i32, y.round() as i32, color);
+    }
+}
+
+fn draw_square(canvas: &mut RgbaImage, cx: i32, cy: i32, radius: i32, color: Rgba<u8>) {
+    for y in cy - radius..=cy + radius {
+        for x in cx - radius..=cx + radius {
+            put_checked(canvas, x, y, color);
+        }
+    }
+}
+
+fn put_checked(canvas: &mut RgbaImage, x: i32, y: i32, color: Rgba<u8>) {
+    if x >= 0 && y >= 0 && (x as u32) < canvas.width() && (y as u32) < canvas.height() {
+        canvas.put_pixel(x as u32, y as u32, color);
+    }
+}
+
+pub(crate) fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, VectorizeError> {
+    let mut bytes = Vec::new();
+    PngEncoder::new(&mut bytes)
+        .write_image(image.as_raw(), image.width(), image.height(), image::ExtendedColorType::Rgba8)
+        .map_err(VectorizeError::Encode)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Vectorizer, VectorizeOptions};
+
+    #[test]
+    fn artifacts_decode_with_expected_sizes() {
+        let image = RgbaImage::from_fn(10, 6, |x, _| {
+            if x < 5 {
+                Rgba([20, 120, 220, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        });
+        let mut vectorizer = Vectorizer::new(image, VectorizeOptions::default());
+        let artifacts = vectorizer.debug_artifacts().expect("artifacts should encode");
+
+        let quantized = image::load_from_memory(&artifacts.quantized_png).unwrap().to_rgba8();
+        assert_eq!(quantized.dimensions(), (10, 6));
+        assert_eq!(quantized.get_pixel(9, 0)[3], 0);
+
+        let labels = image::load_from_memory(&artifacts.label_map_png).unwrap().to_rgba8();
+        assert_eq!(labels.get_pixel(9, 0).0, [0, 0, 0, 255]);
+        assert_ne!(labels.get_pixel(0, 0).0, [0, 0, 0, 255]);
+
+        let overlay = image::load_from_memory(&artifacts.overlay_png).unwrap().to_rgba8();
+        assert_eq!(overlay.width(), 10 * OVERLAY_MAX_SCALE);
+        assert!(overlay.pixels().any(|p| *p == CONTOUR_COLOR));
+    }
+}
@@ -0,0 +1,262 @@
+use image::RgbaImage;
+
+use crate::Point;
+
+/// Sub-rows sampled per pixel row by [`VectorDocument::rasterize`].
+const RASTER_SUBROWS: usize = 4;
+/// Curve flattening tolerance for rasterizing, in pixels.
+const RASTER_FLATTEN_TOLERANCE: f32 = 0.1;
+
+/// Format-independent vector output: paths grouped by palette color, in image coordinates.
+///
+/// This is what every [`Emitter`](crate::Emitter) consumes, so new output formats never have to
+/// re-parse SVG path data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorDocument {
+    pub width: u32,
+    pub height: u32,
+    pub layers: Vec<ColorLayer>,
+}
+
+/// All paths filled with one palette color.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorLayer {
+    pub palette_index: usize,
+    pub color: [u8; 4],
+    /// A kept background (see [`BackgroundOptions::keep_rect`](crate::BackgroundOptions::keep_rect)):
+    /// one rectangle covering the canvas, underneath every other layer.
+    pub background: bool,
+    pub paths: Vec<VectorPath>,
+}
+
+/// One filled shape made of move/line/cubic commands: an outer outline, optionally followed by
+/// hole subpaths that are cut out with the even-odd fill rule.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VectorPath {
+    pub commands: Vec<PathCommand>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathCommand {
+    MoveTo(Point),
+    LineTo(Point),
+    CubicTo { ctrl1: Point, ctrl2: Point, to: Point },
+    Close,
+}
+
+impl VectorDocument {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Returns the layer for `palette_index`, appending an empty one the first time it is seen.
+    pub fn layer_mut(&mut self, palette_index: usize, color: [u8; 4]) -> &mut ColorLayer {
+        let position = match self.layers.iter().position(|layer| layer.palette_index == palette_index) {
+            Some(position) => position,
+            None => {
+                self.layers.push(ColorLayer {
+                    palette_index,
+                    color,
+                    background: false,
+                    paths: Vec::new(),
+                });
+                self.layers.len() - 1
+            }
+        };
+        &mut self.layers[position]
+    }
+
+    pub fn path_count(&self) -> usize {
+        self.layers.iter().map(|layer| layer.paths.len()).sum()
+    }
+
+    /// Renders the document the way a viewer would: paths filled with the even-odd rule,
+    /// anti-aliased (exact horizontal coverage, four sub-rows per pixel), and layers composited
+    /// in order over a transparent canvas.
+    pub fn rasterize(&self) -> RgbaImage {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let mut canvas = vec![[0f32; 4]; width * height];
+        let mut coverage = vec![0f32; width * height];
+
+        for layer in &self.layers {
+            let color = layer.color.map(|channel| f32::from(channel) / 255.0);
+            for path in &layer.paths {
+                let edges: Vec<(Point, Point)> = path
+                    .flatten(RASTER_FLATTEN_TOLERANCE)
+                    .iter()
+                    .flat_map(|polyline| {
+                        let closing = (polyline[polyline.len() - 1], polyline[0]);
+                        polyline.windows(2).map(|w| (w[0], w[1])).chain(std::iter::once(closing))
+                    })
+                    .filter(|(p, q)| p.y != q.y)
+                    .collect();
+                let (min_y, max_y) = edges
+                    .iter()
+                    .flat_map(|(p, q)| [p.y, q.y])
+                    .fold((f32::MAX, f32::MIN), |(lo, hi), y| (lo.min(y), hi.max(y)));
+                if edges.is_empty() {
+                    continue;
+                }
+                let rows = (min_y.floor().max(0.0) as usize)..(max_y.ceil().max(0.0) as usize).min(height);
+
+                for y in rows.clone() {
+                    for sub in 0..RASTER_SUBROWS {
+                        let sample_y = y as f32 + (sub as f32 + 0.5) / RASTER_SUBROWS as f32;
+                        let mut crossings: Vec<f32> = edges
+                            .iter()
+                            .filter(|(p, q)| (p.y <= sample_y) != (q.y <= sample_y))
+                            .map(|(p, q)| p.x + (sample_y - p.y) * (q.x - p.x) / (q.y - p.y))
+                            .collect();
+                        crossings.sort_by(f32::total_cmp);
+                        for span in crossings.chunks_exact(2) {
+                            let (start, end) = (span[0].max(0.0), span[1].min(width as f32));
+                            if start >= end {
+                                continue;
+                            }
+                            for x in start.floor() as usize..(end.ceil() as usize).min(width) {
+                                let overlap = end.min(x as f32 + 1.0) - start.max(x as f32);
+                                coverage[y * width + x] += overlap / RASTER_SUBROWS as f32;
+                            }
+                        }
+                    }
+                }
+
+                // Source-over compositing of this path, then reset its coverage.
+                for y in rows {
+                    for (pixel, covered) in canvas[y * width..(y + 1) * width]
+                        .iter_mut()
+                        .zip(&mut coverage[y * width..(y + 1) * width])
+                    {
+                        let alpha = color[3] * covered.min(1.0);
+                        *covered = 0.0;
+                        if alpha <= 0.0 {
+                            continue;
+                        }
+                        let out_alpha = alpha + pixel[3] * (1.0 - alpha);
+                        for channel in 0..3 {
+                            pixel[channel] =
+                                (color[channel] * alpha + pixel[channel] * pixel[3] * (1.0 - alpha)) / out_alpha;
+                        }
+                        pixel[3] = out_alpha;
+                    }
+                }
+            }
+        }
+
+        let data = canvas.iter().flat_map(|pixel| pixel.map(|channel| (channel * 255.0).round() as u8)).collect();
+        RgbaImage::from_raw(self.width, self.height, data).expect("buffer matches the image size")
+    }
+
+    /// Number of path vertices (every command except `Close`).
+    pub fn node_count(&self) -> usize {
+        self.layers
+            .iter()
+            .flat_map(|layer| &layer.paths)
+            .flat_map(|path| &path.commands)
+            .filter(|command| !matches!(command, PathCommand::Close))
+            .count()
+    }
+}
+
+impl VectorPath {
+    /// Straight-edged closed path through `points`.
+    pub fn polygon(points: &[Point]) -> Self {
+        let mut commands = Vec::with_capacity(points.len() + 1);
+        for (i, &p) in points.iter().enumerate() {
+            commands.push(if i == 0 { PathCommand::MoveTo(p) } else { PathCommand::LineTo(p) });
+        }
+        if !commands.is_empty() {
+            commands.push(PathCommand::Close);
+        }
+        Self { commands }
+    }
+
+    /// Axis-aligned rectangle with its top-left corner at `(x, y)`.
+    pub fn rect(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self::polygon(&[
+            Point::new(x, y),
+            Point::new(x + width, y),
+            Point::new(x + width, y + height),
+            Point::new(x, y + height),
+        ])
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// True when the path has hole subpaths and so needs an even-odd fill.
+    pub fn has_holes(&self) -> bool {
+        self.commands.iter().filter(|command| matches!(command, PathCommand::MoveTo(_))).count() > 1
+    }
+
+    /// Converts the path into polylines, one per subpath, replacing curves with line segments
+    /// that stay within `tolerance` of the true curve. Closed subpaths repeat their first point.
+    pub fn flatten(&self, tolerance: f32) -> Vec<Vec<Point>> {
+        let tolerance = tolerance.max(0.001);
+        let mut polylines = Vec::new();
+        let mut current: Vec<Point> = Vec::new();
+
+        for command in &self.commands {
+            match *command {
+                PathCommand::MoveTo(p) => {
+                    if current.len() > 1 {
+                        polylines.push(std::mem::take(&mut current));
+                    }
+                    current.clear();
+                    current.push(p);
+                }
+                PathCommand::LineTo(p) => current.push(p),
+                PathCommand::CubicTo { ctrl1, ctrl2, to } => {
+                    let Some(&from) = current.last() else {
+                        current.push(to);
+                        continue;
+                    };
+                    flatten_cubic(&mut current, [from, ctrl1, ctrl2, to], tolerance);
+                }
+                PathCommand::Close => {
+                    if let (Some(&first), Some(&last)) = (current.first(), current.last()) {
+                        if first != last {
+                            current.push(first);
+                        }
+                    }
+                    if current.len() > 1 {
+                        polylines.push(std::mem::take(&mut current));
+                    }
+                }
+            }
+        }
+
+        if current.len() > 1 {
+            polylines.push(current);
+        }
+        polylines
+    }
+}
+
+const MAX_CURVE_SEGMENTS: usize = 64;
+
+// Uniform subdivision with the segment count bounded by the curve's second differences,
+// which keeps the chord error under `tolerance`.
+fn flatten_cubic(out: &mut Vec<Point>, [p0, p1, p2, p3]: [Point; 4], tolerance: f32) {
+    let dd = |a: Point, b: Point, c: Point| ((a.x - 2.0 * b.x + c.x).powi(2) + (a.y - 2.0 * b.y + c.y).powi(2)).sqrt();
+    let bend = dd(p0, p1, p2).max(dd(p1, p2, p3));
+    let segments = ((0.75 * bend / tolerance).sqrt().ceil() as usize).clamp(1, MAX_CURVE_SEGMENTS);
+
+    for step in 1..=segments {
+        let t = step as f32 / segments as f32;
+        let mt = 1.0 - t;
+        let a = mt * mt * mt;
+        let b = 3.0 * mt * mt * t;
+        let c = 3.0 * mt * t * t;
+        let d = t * t * t;
+        out.push(Point::new(
+            a * p0.x + b * p1.x + c * p2.x + d * p3.x,
+            a * p0.y + b * p1.y + c * p2.y + d * p3.y,
+        ));
+    }
+}
@@ -0,0 +1,93 @@
+use std::fmt::Write as FmtWrite;
+
+use super::svg::path_data;
+use crate::{opacity_from_options, to_hex, Emitter, VectorDocument, VectorizeOptions};
+
+/// Android `VectorDrawable` XML, sized 1 px = 1 dp.
+///
+/// Each palette color becomes one `<path>` whose `pathData` joins all of that color's outlines,
+/// using `fillType="evenOdd"` when any of them has holes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AndroidVectorEmitter;
+
+impl Emitter for AndroidVectorEmitter {
+    fn emit(&self, document: &VectorDocument, options: &VectorizeOptions) -> String {
+        let mut xml = String::new();
+        writeln!(
+            xml,
+            "<vector xmlns:android=\"http://schemas.android.com/apk/res/android\"\n    \
+             android:width=\"{w}dp\"\n    android:height=\"{h}dp\"\n    \
+             android:viewportWidth=\"{w}\"\n    android:viewportHeight=\"{h}\">",
+            w = document.width,
+            h = document.height
+        )
+        .ok();
+
+        for layer in &document.layers {
+            if layer.paths.is_empty() {
+                continue;
+            }
+            let data: Vec<String> = layer.paths.iter().map(path_data).collect();
+            writeln!(xml, "    <path").ok();
+            writeln!(xml, "        android:fillColor=\"#{}\"", to_hex(layer.color).to_ascii_uppercase()).ok();
+            let opacity = opacity_from_options(layer.color[3], options);
+            if opacity < 1.0 {
+                writeln!(xml, "        android:fillAlpha=\"{opacity:.3}\"").ok();
+            }
+            if layer.paths.iter().any(|path| path.has_holes()) {
+                writeln!(xml, "        android:fillType=\"evenOdd\"").ok();
+            }
+            writeln!(xml, "        android:pathData=\"{}\"/>", data.join(" ")).ok();
+        }
+
+        xml.push_str("</vector>\n");
+        xml
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColorLayer, Point, VectorPath};
+
+    #[test]
+    fn writes_one_path_per_color() {
+        let square = |x: f32, size: f32| {
+            VectorPath::polygon(&[
+                Point::new(x, x),
+                Point::new(x + size, x),
+                Point::new(x + size, x + size),
+                Point::new(x, x + size),
+            ])
+        };
+        let mut ring = square(0.0, 10.0);
+        ring.commands.extend(square(3.0, 4.0).commands);
+        let document = VectorDocument {
+            width: 24,
+            height: 16,
+            layers: vec![
+                ColorLayer {
+                    palette_index: 0,
+                    color: [0xab, 0x12, 0xcd, 128],
+                    background: false,
+                    paths: vec![ring, square(12.0, 2.0)],
+                },
+                ColorLayer {
+                    palette_index: 1,
+                    color: [0, 0, 0, 255],
+                    background: false,
+                    paths: vec![square(20.0, 1.0)],
+                },
+            ],
+        };
+
+        let xml = AndroidVectorEmitter.emit(&document, &VectorizeOptions::default());
+        assert!(xml.contains("android:viewportWidth=\"24\""));
+        assert!(xml.contains("android:viewportHeight=\"16\""));
+        assert_eq!(xml.matches("<path").count(), 2);
+        assert_eq!(xml.matches("android:fillType=\"evenOdd\"").count(), 1);
+        assert_eq!(xml.matches("android:fillAlpha").count(), 1);
+        assert!(xml.contains("android:fillColor=\"#AB12CD\""));
+        assert!(xml.trim_end().ends_with("</vector>"));
+    }
+}
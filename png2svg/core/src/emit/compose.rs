@@ -0,0 +1,129 @@
+use std::fmt::Write as FmtWrite;
+
+use crate::{opacity_from_options, to_hex, Emitter, PathCommand, VectorDocument, VectorizeOptions};
+
+/// Kotlin source for a Jetpack Compose `ImageVector`, sized 1 px = 1 dp.
+///
+/// The vector is a lazily built top-level property with one `path { }` block per palette
+/// color, using `PathFillType.EvenOdd` for colors with holes.
+#[derive(Debug, Clone)]
+pub struct ComposeEmitter {
+    pub name: String,
+}
+
+impl Default for ComposeEmitter {
+    fn default() -> Self {
+        Self {
+            name: "VectorImage".to_string(),
+        }
+    }
+}
+
+impl Emitter for ComposeEmitter {
+    fn emit(&self, document: &VectorDocument, options: &VectorizeOptions) -> String {
+        let name = &self.name;
+        let mut kotlin = String::new();
+        for import in [
+            "androidx.compose.ui.graphics.Color",
+            "androidx.compose.ui.graphics.PathFillType",
+            "androidx.compose.ui.graphics.SolidColor",
+            "androidx.compose.ui.graphics.vector.ImageVector",
+            "androidx.compose.ui.graphics.vector.path",
+            "androidx.compose.ui.unit.dp",
+        ] {
+            writeln!(kotlin, "import {import}").ok();
+        }
+
+        writeln!(kotlin, "\nval {name}: ImageVector by lazy {{").ok();
+        kotlin.push_str("    ImageVector.Builder(\n");
+        writeln!(kotlin, "        name = \"{name}\",").ok();
+        writeln!(kotlin, "        defaultWidth = {}.dp,", document.width).ok();
+        writeln!(kotlin, "        defaultHeight = {}.dp,", document.height).ok();
+        writeln!(kotlin, "        viewportWidth = {}f,", document.width).ok();
+        writeln!(kotlin, "        viewportHeight = {}f,", document.height).ok();
+        kotlin.push_str("    ).apply {\n");
+
+        for layer in &document.layers {
+            if layer.paths.is_empty() {
+                continue;
+            }
+            let opacity = opacity_from_options(layer.color[3], options);
+            let fill_type = if layer.paths.iter().any(|path| path.has_holes()) {
+                "EvenOdd"
+            } else {
+                "NonZero"
+            };
+            kotlin.push_str("        path(\n");
+            writeln!(
+                kotlin,
+                "            fill = SolidColor(Color(0xFF{})),",
+                to_hex(layer.color).to_ascii_uppercase()
+            )
+            .ok();
+            writeln!(kotlin, "            fillAlpha = {opacity:.3}f,").ok();
+            writeln!(kotlin, "            pathFillType = PathFillType.{fill_type},").ok();
+            kotlin.push_str("        ) {\n");
+            for path in &layer.paths {
+                for command in &path.commands {
+                    match *command {
+                        PathCommand::MoveTo(p) => writeln!(kotlin, "            moveTo({:.2}f, {:.2}f)", p.x, p.y),
+                        PathCommand::LineTo(p) => writeln!(kotlin, "            lineTo({:.2}f, {:.2}f)", p.x, p.y),
+                        PathCommand::CubicTo { ctrl1, ctrl2, to } => writeln!(
+                            kotlin,
+                            "            curveTo({:.2}f, {:.2}f, {:.2}f, {:.2}f, {:.2}f, {:.2}f)",
+                            ctrl1.x, ctrl1.y, ctrl2.x, ctrl2.y, to.x, to.y
+                        ),
+                        PathCommand::Close => writeln!(kotlin, "            close()"),
+                    }
+                    .ok();
+                }
+            }
+            kotlin.push_str("        }\n");
+        }
+
+        kotlin.push_str("    }.build()\n}\n");
+        kotlin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColorLayer, Point, VectorPath};
+
+    #[test]
+    fn writes_path_blocks() {
+        let square = |x: f32, size: f32| {
+            VectorPath::polygon(&[
+                Point::new(x, x),
+                Point::new(x + size, x),
+                Point::new(x + size, x + size),
+                Point::new(x, x + size),
+            ])
+        };
+        let mut ring = square(0.0, 8.0);
+        ring.commands.extend(square(2.0, 4.0).commands);
+        let document = VectorDocument {
+            width: 8,
+            height: 8,
+            layers: vec![ColorLayer {
+                palette_index: 0,
+                color: [0x12, 0x34, 0x56, 255],
+                background: false,
+                paths: vec![ring],
+            }],
+        };
+        let emitter = ComposeEmitter {
+            name: "Ring".to_string(),
+        };
+        let kotlin = emitter.emit(&document, &VectorizeOptions::default());
+
+        assert!(kotlin.contains("val Ring: ImageVector by lazy {"));
+        assert!(kotlin.contains("viewportWidth = 8f,"));
+        assert!(kotlin.contains("fill = SolidColor(Color(0xFF123456)),"));
+        assert!(kotlin.contains("pathFillType = PathFillType.EvenOdd,"));
+        assert_eq!(kotlin.matches("moveTo(").count(), 2);
+        assert_eq!(kotlin.matches("close()").count(), 2);
+        assert!(kotlin.ends_with("    }.build()\n}\n"));
+    }
+}
@@ -0,0 +1,270 @@
+use std::fmt::Write as FmtWrite;
+
+use crate::{to_hex, Emitter, Point, VectorDocument, VectorizeOptions};
+
+const MM_PER_INCH: f32 = 25.4;
+
+/// AutoCAD R12 ASCII DXF for CAD and laser-cutting tools.
+///
+/// Every palette color becomes its own layer (named after the hex color, plus alpha for
+/// translucent colors, and drawn with the nearest AutoCAD Color Index) holding closed
+/// `POLYLINE` entities. R12 predates `LWPOLYLINE`/`SPLINE`, so curves are flattened to within
+/// `flatten_tolerance` pixels. The y axis is flipped so the drawing is not mirrored.
+#[derive(Debug, Clone, Copy)]
+pub struct DxfEmitter {
+    /// Pixels per inch used to convert to millimetres; `None` keeps one drawing unit per pixel.
+    pub dpi: Option<f32>,
+    /// Maximum distance, in pixels, between a flattened curve and the original.
+    pub flatten_tolerance: f32,
+}
+
+impl Default for DxfEmitter {
+    fn default() -> Self {
+        Self {
+            dpi: Some(96.0),
+            flatten_tolerance: 0.25,
+        }
+    }
+}
+
+impl DxfEmitter {
+    fn scale(&self) -> f32 {
+        match self.dpi {
+            Some(dpi) if dpi > 0.0 => MM_PER_INCH / dpi,
+            _ => 1.0,
+        }
+    }
+}
+
+impl Emitter for DxfEmitter {
+    fn emit(&self, document: &VectorDocument, _options: &VectorizeOptions) -> String {
+        let scale = self.scale();
+        let height = document.height as f32;
+        let to_drawing = |p: Point| (p.x * scale, (height - p.y) * scale);
+
+        let mut dxf = String::new();
+        group(&mut dxf, 0, "SECTION");
+        group(&mut dxf, 2, "HEADER");
+        group(&mut dxf, 9, "$ACADVER");
+        group(&mut dxf, 1, "AC1009");
+        group(&mut dxf, 9, "$EXTMIN");
+        coords(&mut dxf, (0.0, 0.0));
+        group(&mut dxf, 9, "$EXTMAX");
+        coords(&mut dxf, (document.width as f32 * scale, height * scale));
+        group(&mut dxf, 0, "ENDSEC");
+
+        group(&mut dxf, 0, "SECTION");
+        group(&mut dxf, 2, "TABLES");
+        group(&mut dxf, 0, "TABLE");
+        group(&mut dxf, 2, "LAYER");
+        // Layers of the same color (e.g. duplicate entries in an embedded palette) share a record.
+        let mut names: Vec<(String, [u8; 4])> = Vec::new();
+        for layer in &document.layers {
+            let name = layer_name(layer.color);
+            if !names.iter().any(|(existing, _)| *existing == name) {
+                names.push((name, layer.color));
+            }
+        }
+        group(&mut dxf, 70, names.len());
+        for (name, color) in &names {
+            group(&mut dxf, 0, "LAYER");
+            group(&mut dxf, 2, name);
+            group(&mut dxf, 70, 0);
+            group(&mut dxf, 62, nearest_aci(*color));
+            group(&mut dxf, 6, "CONTINUOUS");
+        }
+        group(&mut dxf, 0, "ENDTAB");
+        group(&mut dxf, 0, "ENDSEC");
+
+        group(&mut dxf, 0, "SECTION");
+        group(&mut dxf, 2, "ENTITIES");
+        for layer in &document.layers {
+            let name = layer_name(layer.color);
+            for path in &layer.paths {
+                for mut polyline in path.flatten(self.flatten_tolerance) {
+                    // Closed polylines list each vertex once.
+                    if polyline.len() > 2 && polyline.first() == polyline.last() {
+                        polyline.pop();
+                    }
+                    if polyline.len() < 2 {
+                        continue;
+                    }
+                    group(&mut dxf, 0, "POLYLINE");
+                    group(&mut dxf, 8, &name);
+                    group(&mut dxf, 66, 1);
+                    coords(&mut dxf, (0.0, 0.0));
+                    group(&mut dxf, 70, 1);
+                    for &p in &polyline {
+                        group(&mut dxf, 0, "VERTEX");
+                        group(&mut dxf, 8, &name);
+                        coords(&mut dxf, to_drawing(p));
+                    }
+                    group(&mut dxf, 0, "SEQEND");
+                    group(&mut dxf, 8, &name);
+                }
+            }
+        }
+        group(&mut dxf, 0, "ENDSEC");
+        group(&mut dxf, 0, "EOF");
+        dxf
+    }
+}
+
+fn group(dxf: &mut String, code: u16, value: impl std::fmt::Display) {
+    writeln!(dxf, "{code:>3}\n{value}").ok();
+}
+
+fn coords(dxf: &mut String, (x, y): (f32, f32)) {
+    group(dxf, 10, format_args!("{x:.4}"));
+    group(dxf, 20, format_args!("{y:.4}"));
+    group(dxf, 30, "0.0");
+}
+
+/// `COLOR_RRGGBB`, or `COLOR_RRGGBB_AA` for translucent colors, which may share their RGB with
+/// another palette entry.
+fn layer_name(color: [u8; 4]) -> String {
+    let rgb = to_hex(color).to_ascii_uppercase();
+    match color[3] {
+        255 => format!("COLOR_{rgb}"),
+        alpha => format!("COLOR_{rgb}_{alpha:02X}"),
+    }
+}
+
+/// Closest AutoCAD Color Index for an RGB color, so CAM tools without layer names still show
+/// something close to the palette.
+fn nearest_aci(color: [u8; 4]) -> u8 {
+    (1..=255u8)
+        .min_by_key(|&index| {
+            let [r, g, b] = aci_rgb(index);
+            let dr = r as i32 - color[0] as i32;
+            let dg = g as i32 - color[1] as i32;
+            let db = b as i32 - color[2] as i32;
+            dr * dr + dg * dg + db * db
+        })
+        .unwrap_or(7)
+}
+
+// Approximates the standard ACI table: 1-9 are fixed colors, 10-249 cycle through 24 hues with
+// five brightness levels at full and half saturation, and 250-255 are grays.
+fn aci_rgb(index: u8) -> [u8; 3] {
+    match index {
+        1 => [255, 0, 0],
+        2 => [255, 255, 0],
+        3 => [0, 255, 0],
+        4 => [0, 255, 255],
+        5 => [0, 0, 255],
+        6 => [255, 0, 255],
+        7 => [255, 255, 255],
+        8 => [128, 128, 128],
+        9 => [192, 192, 192],
+        10..=249 => {
+            let offset = index - 10;
+            let hue = (offset / 10) as f32 * 15.0;
+            let variant = offset % 10;
+            let value = [1.0, 0.8, 0.6, 0.5, 0.3][(variant / 2) as usize];
+            let saturation = if variant.is_multiple_of(2) { 1.0 } else { 0.5 };
+            hsv_to_rgb(hue, saturation, value)
+        }
+        _ => {
+            let level = [51, 91, 132, 173, 214, 255][(index - 250) as usize];
+            [level, level, level]
+        }
+    }
+}
+
+fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> [u8; 3] {
+    let sector = hue / 60.0;
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    let channel = |v: f32| ((v + m) * 255.0).round() as u8;
+    [channel(r), channel(g), channel(b)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColorLayer, PathCommand, VectorPath};
+
+    fn document() -> VectorDocument {
+        let mut curved = VectorPath::polygon(&[Point::new(0.0, 0.0), Point::new(10.0, 0.0)]);
+        curved.commands.insert(
+            2,
+            PathCommand::CubicTo {
+                ctrl1: Point::new(20.0, 5.0),
+                ctrl2: Point::new(20.0, 15.0),
+                to: Point::new(10.0, 20.0),
+            },
+        );
+        VectorDocument {
+            width: 96,
+            height: 48,
+            layers: vec![
+                ColorLayer {
+                    palette_index: 0,
+                    color: [255, 0, 0, 255],
+                    background: false,
+                    paths: vec![VectorPath::polygon(&[
+                        Point::new(0.0, 0.0),
+                        Point::new(96.0, 0.0),
+                        Point::new(96.0, 48.0),
+                    ])],
+                },
+                ColorLayer {
+                    palette_index: 1,
+                    color: [0, 0, 255, 255],
+                    background: false,
+                    paths: vec![curved],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn writes_one_layer_per_color_in_millimetres() {
+        let dxf = DxfEmitter::default().emit(&document(), &VectorizeOptions::default());
+        assert!(dxf.contains("AC1009"));
+        assert!(dxf.contains("COLOR_FF0000\n 70\n0\n 62\n1\n"));
+        assert!(dxf.contains("COLOR_0000FF\n 70\n0\n 62\n5\n"));
+        // 96 px at 96 dpi is one inch; y = 0 maps to the top of the 48 px (12.7 mm) drawing.
+        assert!(dxf.contains(" 10\n25.4000\n 20\n12.7000\n"));
+        assert!(dxf.trim_end().ends_with("EOF"));
+    }
+
+    #[test]
+    fn translucent_and_repeated_colors_get_unique_layers() {
+        let mut document = document();
+        let mut shadow = document.layers[0].clone();
+        shadow.color = [255, 0, 0, 128];
+        document.layers.push(shadow);
+        document.layers.push(document.layers[1].clone());
+        let dxf = DxfEmitter::default().emit(&document, &VectorizeOptions::default());
+        let table = &dxf[dxf.find("LAYER").unwrap()..dxf.find("ENDTAB").unwrap()];
+        assert!(table.starts_with("LAYER\n 70\n3\n"));
+        assert_eq!(table.matches("\n  0\nLAYER\n").count(), 3);
+        assert_eq!(table.matches("COLOR_FF0000_80\n").count(), 1);
+        assert_eq!(dxf.matches("  8\nCOLOR_FF0000_80\n").count(), 5);
+    }
+
+    #[test]
+    fn flattens_curves_into_closed_polylines() {
+        let emitter = DxfEmitter {
+            dpi: None,
+            flatten_tolerance: 0.1,
+        };
+        let dxf = emitter.emit(&document(), &VectorizeOptions::default());
+        let entities = &dxf[dxf.find("ENTITIES").unwrap()..];
+        let blue = entities.split("POLYLINE").nth(2).unwrap();
+        assert!(blue.matches("VERTEX").count() > 6);
+        assert!(blue.contains(" 70\n1\n"));
+        assert!(!entities.contains("SPLINE"));
+    }
+}
@@ -0,0 +1,102 @@
+use std::fmt::Write as FmtWrite;
+
+use crate::{Emitter, PathCommand, VectorDocument, VectorizeOptions};
+
+/// Encapsulated PostScript (Level 2) for print workflows that predate PDF.
+///
+/// The bounding box matches the image (1 px = 1 pt) and each palette color is one `eofill` over all
+/// of its paths (even-odd, so holes stay open). PostScript has no transparency, so palette alpha is ignored.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EpsEmitter;
+
+impl Emitter for EpsEmitter {
+    fn emit(&self, document: &VectorDocument, _options: &VectorizeOptions) -> String {
+        let mut eps = String::new();
+        eps.push_str("%!PS-Adobe-3.0 EPSF-3.0\n");
+        writeln!(eps, "%%BoundingBox: 0 0 {} {}", document.width, document.height).ok();
+        writeln!(eps, "%%HiResBoundingBox: 0 0 {}.0 {}.0", document.width, document.height).ok();
+        eps.push_str("%%Creator: open-vectorizer\n");
+        eps.push_str("%%LanguageLevel: 2\n");
+        eps.push_str("%%Pages: 1\n");
+        eps.push_str("%%EndComments\n");
+        eps.push_str("%%Page: 1 1\n");
+        eps.push_str("gsave\n");
+        // Flip the y axis so path coordinates stay in image space (origin top-left).
+        writeln!(eps, "0 {} translate 1 -1 scale", document.height).ok();
+
+        for layer in &document.layers {
+            if layer.paths.is_empty() {
+                continue;
+            }
+            let [r, g, b, _] = layer.color;
+            writeln!(
+                eps,
+                "{:.4} {:.4} {:.4} setrgbcolor",
+                r as f32 / 255.0,
+                g as f32 / 255.0,
+                b as f32 / 255.0
+            )
+            .ok();
+            eps.push_str("newpath\n");
+            for path in &layer.paths {
+                for command in &path.commands {
+                    match *command {
+                        PathCommand::MoveTo(p) => writeln!(eps, "{:.2} {:.2} moveto", p.x, p.y),
+                        PathCommand::LineTo(p) => writeln!(eps, "{:.2} {:.2} lineto", p.x, p.y),
+                        PathCommand::CubicTo { ctrl1, ctrl2, to } => writeln!(
+                            eps,
+                            "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} curveto",
+                            ctrl1.x, ctrl1.y, ctrl2.x, ctrl2.y, to.x, to.y
+                        ),
+                        PathCommand::Close => writeln!(eps, "closepath"),
+                    }
+                    .ok();
+                }
+            }
+            eps.push_str("eofill\n");
+        }
+
+        eps.push_str("grestore\n");
+        eps.push_str("showpage\n");
+        eps.push_str("%%EOF\n");
+        eps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColorLayer, Point, VectorPath};
+
+    #[test]
+    fn writes_bounding_box_and_path_operators() {
+        let mut path = VectorPath::polygon(&[Point::new(0.5, 0.5), Point::new(4.0, 0.5), Point::new(4.0, 3.0)]);
+        path.commands.insert(
+            2,
+            PathCommand::CubicTo {
+                ctrl1: Point::new(4.0, 1.0),
+                ctrl2: Point::new(4.0, 2.0),
+                to: Point::new(4.0, 2.5),
+            },
+        );
+        let document = VectorDocument {
+            width: 16,
+            height: 9,
+            layers: vec![ColorLayer {
+                palette_index: 0,
+                color: [255, 128, 0, 255],
+                background: false,
+                paths: vec![path],
+            }],
+        };
+
+        let eps = EpsEmitter.emit(&document, &VectorizeOptions::default());
+        assert!(eps.starts_with("%!PS-Adobe-3.0 EPSF-3.0\n"));
+        assert!(eps.contains("%%BoundingBox: 0 0 16 9\n"));
+        assert!(eps.contains("1.0000 0.5020 0.0000 setrgbcolor"));
+        assert!(eps.contains("0.50 0.50 moveto"));
+        assert!(eps.contains("4.00 1.00 4.00 2.00 4.00 2.50 curveto"));
+        assert!(eps.contains("closepath\neofill\n"));
+        assert!(eps.trim_end().ends_with("%%EOF"));
+    }
+}
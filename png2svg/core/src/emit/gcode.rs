@@ -0,0 +1,310 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as FmtWrite;
+
+use serde::{Deserialize, Serialize};
+
+use super::order_for_travel;
+use crate::{to_hex, Emitter, Point, VectorDocument, VectorizeOptions};
+
+const MM_PER_INCH: f32 = 25.4;
+
+/// Feed rate (mm/min) and laser power (GRBL `S` value) for one kind of pass.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LaserPass {
+    pub feed: f32,
+    pub power: f32,
+}
+
+/// Passes for one palette color; `None` skips that pass.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LaserSettings {
+    /// Cut along every outline, holes included.
+    pub outline: Option<LaserPass>,
+    /// Scan-line hatching over the filled area.
+    pub hatch: Option<LaserPass>,
+}
+
+impl Default for LaserSettings {
+    fn default() -> Self {
+        Self {
+            outline: Some(LaserPass {
+                feed: 300.0,
+                power: 1000.0,
+            }),
+            hatch: Some(LaserPass {
+                feed: 1500.0,
+                power: 300.0,
+            }),
+        }
+    }
+}
+
+/// Per-color laser settings keyed by lowercase `rrggbb`, with a fallback for unlisted colors.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LaserProfile {
+    pub default: LaserSettings,
+    pub colors: BTreeMap<String, LaserSettings>,
+}
+
+impl LaserProfile {
+    pub fn settings_for(&self, color: [u8; 4]) -> &LaserSettings {
+        self.colors.get(&to_hex(color)).unwrap_or(&self.default)
+    }
+}
+
+/// G-code for GRBL-style lasers in dynamic power mode (`M4`, with `$32=1`).
+///
+/// Coordinates are millimetres from the bottom-left corner using `dpi`. For each palette color
+/// the fill is hatched first, then the outlines are cut, using that color's [`LaserSettings`].
+/// Hatching uses the even-odd rule over all of the color's subpaths, so holes are skipped.
+#[derive(Debug, Clone)]
+pub struct GcodeEmitter {
+    pub profile: LaserProfile,
+    /// Pixels per inch used to convert to millimetres.
+    pub dpi: f32,
+    /// Distance between hatch lines, in millimetres.
+    pub hatch_spacing: f32,
+    /// Hatch direction in degrees, counter-clockwise from the machine X axis.
+    pub hatch_angle: f32,
+    /// Maximum distance, in pixels, between a flattened curve and the original.
+    pub flatten_tolerance: f32,
+}
+
+impl Default for GcodeEmitter {
+    fn default() -> Self {
+        Self {
+            profile: LaserProfile::default(),
+            dpi: 96.0,
+            hatch_spacing: 0.1,
+            hatch_angle: 0.0,
+            flatten_tolerance: 0.25,
+        }
+    }
+}
+
+impl Emitter for GcodeEmitter {
+    fn emit(&self, document: &VectorDocument, _options: &VectorizeOptions) -> String {
+        let scale = MM_PER_INCH / self.dpi.max(1.0);
+        let height = document.height as f32;
+        let to_machine = |p: Point| Point::new(p.x * scale, (height - p.y) * scale);
+
+        let mut gcode = String::new();
+        gcode.push_str("; png2svg laser G-code (GRBL, laser mode $32=1)\n");
+        writeln!(
+            gcode,
+            "; {:.3} x {:.3} mm",
+            document.width as f32 * scale,
+            height * scale
+        )
+        .ok();
+        gcode.push_str("G21\nG90\nM4 S0\n");
+
+        let mut position = Point::new(0.0, 0.0);
+        for layer in &document.layers {
+            let settings = self.profile.settings_for(layer.color);
+            if settings.outline.is_none() && settings.hatch.is_none() {
+                continue;
+            }
+            let polylines: Vec<Vec<Point>> = layer
+                .paths
+                .iter()
+                .flat_map(|path| path.flatten(self.flatten_tolerance))
+                .map(|polyline| polyline.into_iter().map(to_machine).collect())
+                .collect();
+            if polylines.is_empty() {
+                continue;
+            }
+
+            writeln!(gcode, "; color #{}", to_hex(layer.color)).ok();
+            if let Some(pass) = settings.hatch {
+                let lines = hatch_lines(&polylines, self.hatch_spacing, self.hatch_angle);
+                write_pass(&mut gcode, &lines, pass, &mut position);
+            }
+            if let Some(pass) = settings.outline {
+                let ordered = order_for_travel(polylines, &mut position);
+                write_pass(&mut gcode, &ordered, pass, &mut position);
+            }
+        }
+
+        gcode.push_str("M5\nG0 X0 Y0\n");
+        gcode
+    }
+}
+
+/// Writes each polyline as a rapid move to its start followed by burning moves.
+fn write_pass(gcode: &mut String, polylines: &[Vec<Point>], pass: LaserPass, position: &mut Point) {
+    let mut first = true;
+    for polyline in polylines {
+        let Some((&start, rest)) = polyline.split_first() else {
+            continue;
+        };
+        writeln!(gcode, "G0 X{:.3} Y{:.3}", start.x, start.y).ok();
+        for p in rest {
+            if first {
+                writeln!(gcode, "G1 X{:.3} Y{:.3} F{:.0} S{:.0}", p.x, p.y, pass.feed, pass.power).ok();
+                first = false;
+            } else {
+                writeln!(gcode, "G1 X{:.3} Y{:.3}", p.x, p.y).ok();
+            }
+        }
+        if let Some(&last) = polyline.last() {
+            *position = last;
+        }
+    }
+}
+
+/// Even-odd scan-line hatching of closed `polylines`, as two-point segments in serpentine order.
+fn hatch_lines(polylines: &[Vec<Point>], spacing: f32, angle_degrees: f32) -> Vec<Vec<Point>> {
+    let spacing = spacing.max(0.001);
+    let (sin, cos) = angle_degrees.to_radians().sin_cos();
+    // Rotate so hatch lines run along u, then sweep v.
+    let to_hatch = |p: Point| Point::new(p.x * cos + p.y * sin, -p.x * sin + p.y * cos);
+    let from_hatch = |u: f32, v: f32| Point::new(u * cos - v * sin, u * sin + v * cos);
+
+    let rotated: Vec<Vec<Point>> = polylines
+        .iter()
+        .map(|polyline| polyline.iter().copied().map(to_hatch).collect())
+        .collect();
+    let (min_v, max_v) = rotated
+        .iter()
+        .flatten()
+        .fold((f32::MAX, f32::MIN), |(lo, hi), p| (lo.min(p.y), hi.max(p.y)));
+    if min_v > max_v {
+        return Vec::new();
+    }
+
+    let first_v = min_v + spacing / 2.0;
+    let line_count = ((max_v - first_v) / spacing).floor() as i64 + 1;
+    if line_count <= 0 {
+        return Vec::new();
+    }
+    let line_v = |line: i64| first_v + line as f32 * spacing;
+
+    // Bucket edge crossings per scan line; an edge crosses line v when lo <= v < hi.
+    let mut crossings: Vec<Vec<f32>> = vec![Vec::new(); line_count as usize];
+    for polyline in &rotated {
+        for edge in polyline.windows(2) {
+            let (a, b) = (edge[0], edge[1]);
+            let (lo, hi) = (a.y.min(b.y), a.y.max(b.y));
+            if lo == hi {
+                continue;
+            }
+            let first = (((lo - first_v) / spacing).ceil() as i64).max(0);
+            let last = (((hi - first_v) / spacing).ceil() as i64 - 1).min(line_count - 1);
+            for line in first..=last {
+                let v = line_v(line);
+                if (lo..hi).contains(&v) {
+                    crossings[line as usize].push(a.x + (v - a.y) * (b.x - a.x) / (b.y - a.y));
+                }
+            }
+        }
+    }
+
+    let mut segments = Vec::new();
+    for (line, mut us) in crossings.into_iter().enumerate() {
+        us.sort_by(f32::total_cmp);
+        let v = line_v(line as i64);
+        let mut spans: Vec<(f32, f32)> = us.chunks_exact(2).map(|pair| (pair[0], pair[1])).collect();
+        if line % 2 == 1 {
+            spans = spans.into_iter().rev().map(|(start, end)| (end, start)).collect();
+        }
+        segments.extend(spans.into_iter().map(|(start, end)| vec![from_hatch(start, v), from_hatch(end, v)]));
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColorLayer, VectorPath};
+
+    fn square(x: f32, y: f32, size: f32) -> Vec<Point> {
+        vec![
+            Point::new(x, y),
+            Point::new(x + size, y),
+            Point::new(x + size, y + size),
+            Point::new(x, y + size),
+            Point::new(x, y),
+        ]
+    }
+
+    #[test]
+    fn hatching_skips_holes() {
+        let polylines = [square(0.0, 0.0, 10.0), square(4.0, 4.0, 2.0)];
+        let segments = hatch_lines(&polylines, 1.0, 0.0);
+        assert_eq!(segments.len(), 10 + 2);
+
+        // Line at y = 4.5 crosses the hole: two spans, neither covering x = 5.
+        let through_hole: Vec<&Vec<Point>> = segments.iter().filter(|s| s[0].y == 4.5).collect();
+        assert_eq!(through_hole.len(), 2);
+        for segment in through_hole {
+            let (lo, hi) = (segment[0].x.min(segment[1].x), segment[0].x.max(segment[1].x));
+            assert!(!(lo..=hi).contains(&5.0));
+        }
+    }
+
+    #[test]
+    fn rotated_hatching_stays_inside() {
+        let segments = hatch_lines(&[square(0.0, 0.0, 10.0)], 0.5, 45.0);
+        assert!(!segments.is_empty());
+        for p in segments.iter().flatten() {
+            assert!((-0.01..=10.01).contains(&p.x) && (-0.01..=10.01).contains(&p.y), "{p:?}");
+        }
+    }
+
+    #[test]
+    fn per_color_settings_select_passes() {
+        let document = VectorDocument {
+            width: 96,
+            height: 96,
+            layers: vec![
+                ColorLayer {
+                    palette_index: 0,
+                    color: [255, 255, 255, 255],
+                    background: false,
+                    paths: vec![VectorPath::polygon(&square(0.0, 0.0, 96.0)[..4])],
+                },
+                ColorLayer {
+                    palette_index: 1,
+                    color: [0, 0, 0, 255],
+                    background: false,
+                    paths: vec![VectorPath::polygon(&square(0.0, 0.0, 48.0)[..4])],
+                },
+            ],
+        };
+        let mut profile = LaserProfile::default();
+        profile.colors.insert(
+            "ffffff".to_string(),
+            LaserSettings {
+                outline: None,
+                hatch: None,
+            },
+        );
+        profile.colors.insert(
+            "000000".to_string(),
+            LaserSettings {
+                outline: Some(LaserPass {
+                    feed: 200.0,
+                    power: 900.0,
+                }),
+                hatch: None,
+            },
+        );
+        let emitter = GcodeEmitter {
+            profile,
+            ..GcodeEmitter::default()
+        };
+        let gcode = emitter.emit(&document, &VectorizeOptions::default());
+
+        assert!(!gcode.contains("#ffffff"));
+        assert!(gcode.contains("; color #000000"));
+        assert_eq!(gcode.matches(" F").count(), 1);
+        assert!(gcode.contains("F200 S900"));
+        // 48 px at 96 dpi is half an inch; the image is one inch tall, so the square's top edge
+        // is at y = 25.4 mm and its bottom-right corner at (12.7, 12.7).
+        assert!(gcode.contains("\nG1 X12.700 Y25.400\nG1 X12.700 Y12.700\n"), "{gcode}");
+        assert!(gcode.ends_with("M5\nG0 X0 Y0\n"));
+    }
+}
@@ -0,0 +1,276 @@
+use std::str::FromStr;
+
+use serde_json::{json, Value};
+
+use crate::{to_hex, Emitter, Point, VectorDocument, VectorizeOptions};
+
+/// ESRI world file (`.pgw`, `.wld`, ...): the affine transform from pixel to map coordinates.
+///
+/// The six lines are `A` (x pixel size), `D`, `B` (rotation terms), `E` (y pixel size, usually
+/// negative) and `C`, `F` (map coordinates of the *center* of the top-left pixel).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldFile {
+    pub a: f64,
+    pub d: f64,
+    pub b: f64,
+    pub e: f64,
+    pub c: f64,
+    pub f: f64,
+}
+
+impl WorldFile {
+    /// Map coordinates of a point in image space, where pixel `(0, 0)` spans `0..1` on both axes.
+    pub fn transform(&self, p: Point) -> (f64, f64) {
+        let x = f64::from(p.x) - 0.5;
+        let y = f64::from(p.y) - 0.5;
+        (self.a * x + self.b * y + self.c, self.d * x + self.e * y + self.f)
+    }
+
+    /// Conventional world file extensions for an image extension: `png` → `pgw`, `pngw`, `wld`.
+    pub fn sidecar_extensions(image_extension: &str) -> Vec<String> {
+        let ext = image_extension.to_ascii_lowercase();
+        let mut extensions = Vec::new();
+        if let (Some(first), Some(last)) = (ext.chars().next(), ext.chars().last()) {
+            extensions.push(format!("{first}{last}w"));
+            extensions.push(format!("{ext}w"));
+        }
+        extensions.push("wld".to_string());
+        extensions
+    }
+}
+
+impl FromStr for WorldFile {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let numbers = value
+            .split_whitespace()
+            .map(|token| token.parse::<f64>().map_err(|_| format!("world file value {token:?} is not a number")))
+            .collect::<Result<Vec<_>, _>>()?;
+        let &[a, d, b, e, c, f] = numbers.as_slice() else {
+            return Err(format!("world file must hold 6 numbers, found {}", numbers.len()));
+        };
+        Ok(WorldFile { a, d, b, e, c, f })
+    }
+}
+
+/// GeoJSON `FeatureCollection` with one feature per traced region.
+///
+/// Each region is a `Polygon` (or a `MultiPolygon` if its outlines do not nest into one) with
+/// its holes as inner rings, wound per RFC 7946. Properties carry the palette color and the
+/// region's area in output units. Without a world file coordinates are image pixels, y down.
+#[derive(Debug, Clone)]
+pub struct GeoJsonEmitter {
+    pub world_file: Option<WorldFile>,
+    /// Maximum distance, in pixels, between a flattened curve and the original.
+    pub flatten_tolerance: f32,
+}
+
+impl Default for GeoJsonEmitter {
+    fn default() -> Self {
+        Self {
+            world_file: None,
+            flatten_tolerance: 0.25,
+        }
+    }
+}
+
+impl Emitter for GeoJsonEmitter {
+    fn emit(&self, document: &VectorDocument, _options: &VectorizeOptions) -> String {
+        let transform = |p: Point| match &self.world_file {
+            Some(world_file) => world_file.transform(p),
+            None => (f64::from(p.x), f64::from(p.y)),
+        };
+
+        let mut features = Vec::new();
+        for layer in &document.layers {
+            for path in &layer.paths {
+                let rings: Vec<Vec<(f64, f64)>> = path
+                    .flatten(self.flatten_tolerance)
+                    .into_iter()
+                    .map(|polyline| polyline.into_iter().map(transform).collect())
+                    .filter_map(close_ring)
+                    .collect();
+                let polygons = nest_rings(rings);
+                if polygons.is_empty() {
+                    continue;
+                }
+
+                let area: f64 = polygons
+                    .iter()
+                    .flat_map(|polygon| polygon.iter().enumerate())
+                    .map(|(i, ring)| if i == 0 { signed_area(ring).abs() } else { -signed_area(ring).abs() })
+                    .sum();
+                let coordinates = |polygon: &[Vec<(f64, f64)>]| -> Value {
+                    polygon.iter().map(|ring| ring.iter().map(|&(x, y)| json!([round(x), round(y)])).collect::<Value>()).collect()
+                };
+                let geometry = match polygons.as_slice() {
+                    [polygon] => json!({ "type": "Polygon", "coordinates": coordinates(polygon) }),
+                    _ => json!({
+                        "type": "MultiPolygon",
+                        "coordinates": polygons.iter().map(|polygon| coordinates(polygon)).collect::<Vec<_>>(),
+                    }),
+                };
+                features.push(json!({
+                    "type": "Feature",
+                    "properties": {
+                        "color": format!("#{}", to_hex(layer.color)),
+                        "palette_index": layer.palette_index,
+                        "area": round(area),
+                    },
+                    "geometry": geometry,
+                }));
+            }
+        }
+
+        json!({ "type": "FeatureCollection", "features": features }).to_string()
+    }
+}
+
+/// Closes a polyline into a ring, dropping degenerate ones.
+fn close_ring(mut ring: Vec<(f64, f64)>) -> Option<Vec<(f64, f64)>> {
+    if ring.first() != ring.last() {
+        ring.push(ring[0]);
+    }
+    (ring.len() >= 4 && signed_area(&ring) != 0.0).then_some(ring)
+}
+
+/// Groups rings into polygons: rings nested an even number of times are exteriors, the others
+/// holes of their smallest enclosing exterior. Exteriors are wound counter-clockwise and holes
+/// clockwise (in a y-up frame).
+fn nest_rings(rings: Vec<Vec<(f64, f64)>>) -> Vec<Vec<Vec<(f64, f64)>>> {
+    let inside = |inner: &[(f64, f64)], outer: &[(f64, f64)]| {
+        // Outlines of neighbouring rings can touch at corners, so go by the majority of vertices.
+        let vertices = &inner[1..];
+        let contained = vertices.iter().filter(|&&p| contains(outer, p)).count();
+        contained * 2 > vertices.len()
+    };
+    let parents: Vec<Vec<usize>> = rings
+        .iter()
+        .enumerate()
+        .map(|(i, ring)| (0..rings.len()).filter(|&j| j != i && inside(ring, &rings[j])).collect())
+        .collect();
+
+    let mut polygons: Vec<Vec<Vec<(f64, f64)>>> = Vec::new();
+    let mut exterior_of = vec![None; rings.len()];
+    for (i, ring) in rings.iter().enumerate() {
+        if parents[i].len().is_multiple_of(2) {
+            exterior_of[i] = Some(polygons.len());
+            polygons.push(vec![wound(ring.clone(), true)]);
+        }
+    }
+    for (i, ring) in rings.into_iter().enumerate() {
+        if exterior_of[i].is_some() {
+            continue;
+        }
+        let smallest = parents[i]
+            .iter()
+            .filter_map(|&j| exterior_of[j].map(|polygon| (polygon, signed_area(&polygons[polygon][0]).abs())))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        match smallest {
+            Some((polygon, _)) => polygons[polygon].push(wound(ring, false)),
+            None => polygons.push(vec![wound(ring, true)]),
+        }
+    }
+    polygons
+}
+
+fn wound(mut ring: Vec<(f64, f64)>, counter_clockwise: bool) -> Vec<(f64, f64)> {
+    if (signed_area(&ring) > 0.0) != counter_clockwise {
+        ring.reverse();
+    }
+    ring
+}
+
+/// Shoelace area of a closed ring; positive when counter-clockwise in a y-up frame.
+fn signed_area(ring: &[(f64, f64)]) -> f64 {
+    ring.windows(2).map(|w| w[0].0 * w[1].1 - w[1].0 * w[0].1).sum::<f64>() / 2.0
+}
+
+/// Even-odd point-in-polygon test.
+fn contains(ring: &[(f64, f64)], (x, y): (f64, f64)) -> bool {
+    let mut inside = false;
+    for w in ring.windows(2) {
+        let ((x0, y0), (x1, y1)) = (w[0], w[1]);
+        if (y0 > y) != (y1 > y) && x < x0 + (y - y0) * (x1 - x0) / (y1 - y0) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+fn round(value: f64) -> f64 {
+    (value * 1e6).round() / 1e6
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColorLayer, VectorPath};
+
+    fn square(x: f32, size: f32) -> VectorPath {
+        VectorPath::polygon(&[
+            Point::new(x, x),
+            Point::new(x + size, x),
+            Point::new(x + size, x + size),
+            Point::new(x, x + size),
+        ])
+    }
+
+    fn features(emitter: &GeoJsonEmitter, paths: Vec<VectorPath>) -> Vec<Value> {
+        let document = VectorDocument {
+            width: 10,
+            height: 10,
+            layers: vec![ColorLayer {
+                palette_index: 2,
+                color: [0, 128, 0, 255],
+                background: false,
+                paths,
+            }],
+        };
+        let json: Value = serde_json::from_str(&emitter.emit(&document, &VectorizeOptions::default())).unwrap();
+        assert_eq!(json["type"], "FeatureCollection");
+        json["features"].as_array().unwrap().clone()
+    }
+
+    #[test]
+    fn holes_become_inner_rings() {
+        let mut ring = square(0.0, 8.0);
+        ring.commands.extend(square(2.0, 4.0).commands);
+        let features = features(&GeoJsonEmitter::default(), vec![ring, square(9.0, 1.0)]);
+        assert_eq!(features.len(), 2);
+
+        let donut = &features[0];
+        assert_eq!(donut["properties"]["color"], "#008000");
+        assert_eq!(donut["properties"]["palette_index"], 2);
+        assert_eq!(donut["properties"]["area"], 48.0);
+        assert_eq!(donut["geometry"]["type"], "Polygon");
+        let rings = donut["geometry"]["coordinates"].as_array().unwrap();
+        assert_eq!(rings.len(), 2);
+        assert_eq!(rings[0][0], rings[0][4]);
+        let area = |ring: &Value| {
+            let points: Vec<(f64, f64)> =
+                ring.as_array().unwrap().iter().map(|p| (p[0].as_f64().unwrap(), p[1].as_f64().unwrap())).collect();
+            signed_area(&points)
+        };
+        assert!(area(&rings[0]) > 0.0);
+        assert!(area(&rings[1]) < 0.0);
+    }
+
+    #[test]
+    fn world_file_maps_pixel_corners() {
+        let world_file: WorldFile = "2.0\n0.0\n0.0\n-2.0\n1001.0\n4999.0\n".parse().unwrap();
+        assert_eq!(world_file.transform(Point::new(0.0, 0.0)), (1000.0, 5000.0));
+        assert!("1 2 3".parse::<WorldFile>().is_err());
+        assert_eq!(WorldFile::sidecar_extensions("PNG"), ["pgw", "pngw", "wld"]);
+
+        let emitter = GeoJsonEmitter {
+            world_file: Some(world_file),
+            ..GeoJsonEmitter::default()
+        };
+        let features = features(&emitter, vec![square(0.0, 1.0)]);
+        let ring = &features[0]["geometry"]["coordinates"][0];
+        assert_eq!(ring[0], json!([1000.0, 5000.0]));
+        assert_eq!(features[0]["properties"]["area"], 4.0);
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as FmtWrite;
+
+use serde::{Deserialize, Serialize};
+
+use super::order_for_travel;
+use crate::{color_distance, to_hex, Emitter, Point, VectorDocument, VectorizeOptions};
+
+/// HP-GL plotter units per inch.
+const PLOTTER_UNITS_PER_INCH: f32 = 1016.0;
+
+/// Assignment of palette colors (lowercase `rrggbb`) to plotter pen numbers.
+///
+/// Save it between runs so the same color always lands on the same pen. Colors that are not
+/// mapped yet get the lowest free pen; once all `max_pens` are in use they share the pen of the
+/// closest mapped color.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PenMap {
+    pub max_pens: u8,
+    pub colors: BTreeMap<String, u8>,
+}
+
+impl Default for PenMap {
+    fn default() -> Self {
+        Self {
+            max_pens: 8,
+            colors: BTreeMap::new(),
+        }
+    }
+}
+
+impl PenMap {
+    /// Pen for `color`, without assigning one if it is unmapped.
+    pub fn get(&self, color: [u8; 4]) -> Option<u8> {
+        self.colors.get(&to_hex(color)).copied()
+    }
+
+    /// Pen for `color`, assigning and remembering one if needed.
+    pub fn assign(&mut self, color: [u8; 4]) -> u8 {
+        if let Some(pen) = self.get(color) {
+            return pen;
+        }
+        let max_pens = self.max_pens.max(1);
+        let pen = match (1..=max_pens).find(|pen| !self.colors.values().any(|used| used == pen)) {
+            Some(free) => free,
+            None => self.closest_pen(color).unwrap_or(1),
+        };
+        self.colors.insert(to_hex(color), pen);
+        pen
+    }
+
+    /// Assigns pens to every color in `document` that is not mapped yet.
+    pub fn extend_from(&mut self, document: &VectorDocument) {
+        for layer in &document.layers {
+            self.assign(layer.color);
+        }
+    }
+
+    fn closest_pen(&self, color: [u8; 4]) -> Option<u8> {
+        self.colors
+            .iter()
+            .filter_map(|(hex, &pen)| Some((parse_hex(hex)?, pen)))
+            .min_by_key(|&(known, _)| color_distance(known, color))
+            .map(|(_, pen)| pen)
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<[u8; 4]> {
+    let hex = hex.trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |range: std::ops::Range<usize>| u8::from_str_radix(hex.get(range)?, 16).ok();
+    Some([channel(0..2)?, channel(2..4)?, channel(4..6)?, 255])
+}
+
+/// HP-GL/1 outlines for pen plotters.
+///
+/// Curves are flattened to within `flatten_tolerance` pixels, pens follow [`PenMap`], and within
+/// each pen the outlines are ordered nearest-neighbour (closed outlines start at their vertex
+/// closest to the pen) to keep pen-up travel short.
+#[derive(Debug, Clone)]
+pub struct HpglEmitter {
+    pub pen_map: PenMap,
+    /// Pixels per inch used to convert to plotter units (1016 per inch).
+    pub dpi: f32,
+    /// Maximum distance, in pixels, between a flattened curve and the original.
+    pub flatten_tolerance: f32,
+}
+
+impl Default for HpglEmitter {
+    fn default() -> Self {
+        Self {
+            pen_map: PenMap::default(),
+            dpi: 96.0,
+            flatten_tolerance: 0.25,
+        }
+    }
+}
+
+impl Emitter for HpglEmitter {
+    fn emit(&self, document: &VectorDocument, _options: &VectorizeOptions) -> String {
+        let scale = PLOTTER_UNITS_PER_INCH / self.dpi.max(1.0);
+        let height = document.height as f32;
+        let to_plotter = |p: Point| ((p.x * scale).round() as i64, ((height - p.y) * scale).round() as i64);
+
+        // Unmapped colors get pens on a private copy; callers that want to keep the assignment
+        // should run `PenMap::extend_from` first.
+        let mut pen_map = self.pen_map.clone();
+        let mut by_pen: BTreeMap<u8, Vec<Vec<Point>>> = BTreeMap::new();
+        for layer in &document.layers {
+            let pen = pen_map.assign(layer.color);
+            let polylines = by_pen.entry(pen).or_default();
+            for path in &layer.paths {
+                polylines.extend(path.flatten(self.flatten_tolerance));
+            }
+        }
+
+        let mut hpgl = String::from("IN;\n");
+        let mut position = Point::new(0.0, height);
+        for (pen, polylines) in by_pen {
+            writeln!(hpgl, "SP{pen};").ok();
+            for polyline in order_for_travel(polylines, &mut position) {
+                let (x, y) = to_plotter(polyline[0]);
+                write!(hpgl, "PU{x},{y};PD").ok();
+                for (i, &p) in polyline.iter().skip(1).enumerate() {
+                    let (x, y) = to_plotter(p);
+                    if i > 0 {
+                        hpgl.push(',');
+                    }
+                    write!(hpgl, "{x},{y}").ok();
+                }
+                hpgl.push_str(";\n");
+            }
+        }
+        hpgl.push_str("PU;SP0;\n");
+        hpgl
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColorLayer, VectorPath};
+
+    fn square(x: f32, y: f32) -> VectorPath {
+        VectorPath::polygon(&[
+            Point::new(x, y),
+            Point::new(x + 2.0, y),
+            Point::new(x + 2.0, y + 2.0),
+            Point::new(x, y + 2.0),
+        ])
+    }
+
+    #[test]
+    fn pen_map_reuses_and_shares_pens() {
+        let mut pens = PenMap {
+            max_pens: 2,
+            ..PenMap::default()
+        };
+        assert_eq!(pens.assign([255, 0, 0, 255]), 1);
+        assert_eq!(pens.assign([0, 0, 255, 255]), 2);
+        assert_eq!(pens.assign([255, 0, 0, 255]), 1);
+        // Out of pens: dark red shares the red pen.
+        assert_eq!(pens.assign([200, 0, 0, 255]), 1);
+
+        let json = serde_json::to_string(&pens).unwrap();
+        let restored: PenMap = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, pens);
+    }
+
+    #[test]
+    fn orders_paths_by_proximity() {
+        let document = VectorDocument {
+            width: 100,
+            height: 100,
+            layers: vec![ColorLayer {
+                palette_index: 0,
+                color: [0, 0, 0, 255],
+                background: false,
+                paths: vec![square(90.0, 0.0), square(0.0, 96.0), square(40.0, 50.0)],
+            }],
+        };
+        let emitter = HpglEmitter {
+            dpi: PLOTTER_UNITS_PER_INCH,
+            ..HpglEmitter::default()
+        };
+        let hpgl = emitter.emit(&document, &VectorizeOptions::default());
+        let starts: Vec<&str> = hpgl.lines().filter_map(|line| line.strip_prefix("PU")).collect();
+        // Pen starts at the origin (bottom-left), so the square at the bottom-left comes first.
+        assert_eq!(starts[0], "0,2;PD0,4,2,4,2,2,0,2;", "{hpgl}");
+        assert!(starts[1].starts_with("4"), "{hpgl}");
+        assert!(hpgl.starts_with("IN;\nSP1;\n"));
+        assert!(hpgl.ends_with("PU;SP0;\n"));
+    }
+}
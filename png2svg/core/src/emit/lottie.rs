@@ -0,0 +1,272 @@
+use serde_json::{json, Value};
+
+use crate::emit::display_delay_ms;
+use crate::{
+    opacity_from_options, to_hex, AnimationFrame, ColorLayer, Emitter, PathCommand, Point, VectorDocument, VectorPath,
+    VectorizeOptions,
+};
+
+/// Lottie (Bodymovin 5) JSON with one shape layer per palette color.
+///
+/// A still image is a one-frame composition; [`LottieEmitter::emit_frames`] gives every frame
+/// its own set of layers, visible for that frame's delay. Curve control points become vertex
+/// in/out tangents, and colors with holes use the even-odd fill rule.
+#[derive(Debug, Clone)]
+pub struct LottieEmitter {
+    /// Composition frame rate; frame delays are rounded to whole frames.
+    pub frame_rate: f32,
+}
+
+impl Default for LottieEmitter {
+    fn default() -> Self {
+        Self { frame_rate: 30.0 }
+    }
+}
+
+impl Emitter for LottieEmitter {
+    fn emit(&self, document: &VectorDocument, options: &VectorizeOptions) -> String {
+        let layers = shape_layers(document, options, 0, 1, "");
+        composition(document, layers, self.frame_rate, 1)
+    }
+
+    /// Composition that shows each frame in turn for its delay (delays of 10 ms or less count as
+    /// 100 ms, as in browsers; at least one frame long).
+    fn emit_frames(&self, frames: &[AnimationFrame], options: &VectorizeOptions) -> String {
+        let Some(first) = frames.first() else {
+            return composition(&VectorDocument::new(0, 0), Vec::new(), self.frame_rate, 1);
+        };
+
+        let mut layers = Vec::new();
+        let mut elapsed_ms = 0u64;
+        let mut start = 0;
+        for (i, frame) in frames.iter().enumerate() {
+            elapsed_ms += display_delay_ms(frame.delay_ms);
+            let end = ((elapsed_ms as f32 * self.frame_rate / 1000.0).round() as u32).max(start + 1);
+            let name = format!("frame {} ", i + 1);
+            // Earlier frames end before later ones start, so stacking order between them is moot.
+            layers.extend(shape_layers(&frame.document, options, start, end, &name));
+            start = end;
+        }
+        composition(&first.document, layers, self.frame_rate, start)
+    }
+}
+
+fn composition(document: &VectorDocument, mut layers: Vec<Value>, frame_rate: f32, end: u32) -> String {
+    for (i, layer) in layers.iter_mut().enumerate() {
+        layer["ind"] = json!(i + 1);
+    }
+    let lottie = json!({
+        "v": "5.7.0",
+        "fr": frame_rate,
+        "ip": 0,
+        "op": end,
+        "w": document.width,
+        "h": document.height,
+        "nm": "png2svg",
+        "ddd": 0,
+        "assets": [],
+        "layers": layers,
+    });
+    lottie.to_string()
+}
+
+/// Shape layers for `document`, topmost first as Lottie expects.
+fn shape_layers(document: &VectorDocument, options: &VectorizeOptions, start: u32, end: u32, prefix: &str) -> Vec<Value> {
+    document
+        .layers
+        .iter()
+        .rev()
+        .filter(|layer| !layer.paths.is_empty())
+        .map(|layer| shape_layer(layer, options, start, end, prefix))
+        .collect()
+}
+
+fn shape_layer(layer: &ColorLayer, options: &VectorizeOptions, start: u32, end: u32, prefix: &str) -> Value {
+    let [r, g, b, a] = layer.color;
+    let channel = |value: u8| f64::from(value) / 255.0;
+    let opacity = (opacity_from_options(a, options) * 100.0).round();
+    let even_odd = layer.paths.iter().any(VectorPath::has_holes);
+
+    let mut items: Vec<Value> = layer.paths.iter().flat_map(subpaths).map(|shape| json!({
+        "ty": "sh",
+        "ks": { "a": 0, "k": shape },
+    })).collect();
+    items.push(json!({
+        "ty": "fl",
+        "c": { "a": 0, "k": [channel(r), channel(g), channel(b), 1] },
+        "o": { "a": 0, "k": opacity },
+        "r": if even_odd { 2 } else { 1 },
+    }));
+    items.push(json!({
+        "ty": "tr",
+        "p": { "a": 0, "k": [0, 0] },
+        "a": { "a": 0, "k": [0, 0] },
+        "s": { "a": 0, "k": [100, 100] },
+        "r": { "a": 0, "k": 0 },
+        "o": { "a": 0, "k": 100 },
+    }));
+
+    json!({
+        "ddd": 0,
+        "ty": 4,
+        "nm": format!("{prefix}#{}", to_hex(layer.color)),
+        "sr": 1,
+        "ks": {
+            "o": { "a": 0, "k": 100 },
+            "r": { "a": 0, "k": 0 },
+            "p": { "a": 0, "k": [0, 0, 0] },
+            "a": { "a": 0, "k": [0, 0, 0] },
+            "s": { "a": 0, "k": [100, 100, 100] },
+        },
+        "ao": 0,
+        "shapes": [{ "ty": "gr", "nm": "paths", "it": items }],
+        "ip": start,
+        "op": end,
+        "st": 0,
+        "bm": 0,
+    })
+}
+
+/// Lottie shape data (`v` vertices with `i`/`o` tangents relative to them) for each subpath.
+fn subpaths(path: &VectorPath) -> Vec<Value> {
+    #[derive(Default)]
+    struct Shape {
+        vertices: Vec<Point>,
+        in_tangents: Vec<Point>,
+        out_tangents: Vec<Point>,
+        closed: bool,
+    }
+
+    impl Shape {
+        fn push(&mut self, vertex: Point, in_tangent: Point) {
+            self.vertices.push(vertex);
+            self.in_tangents.push(in_tangent);
+            self.out_tangents.push(Point::new(0.0, 0.0));
+        }
+
+        fn into_json(mut self) -> Value {
+            // A closing vertex on top of the first one is implied by `c`; keep its tangent.
+            if self.closed && self.vertices.len() > 1 && self.vertices.first() == self.vertices.last() {
+                self.vertices.pop();
+                self.out_tangents.pop();
+                self.in_tangents[0] = self.in_tangents.pop().unwrap_or(Point::new(0.0, 0.0));
+            }
+            let pairs = |points: &[Point]| -> Vec<[f64; 2]> {
+                points.iter().map(|p| [round(p.x), round(p.y)]).collect()
+            };
+            json!({
+                "i": pairs(&self.in_tangents),
+                "o": pairs(&self.out_tangents),
+                "v": pairs(&self.vertices),
+                "c": self.closed,
+            })
+        }
+    }
+
+    let origin = Point::new(0.0, 0.0);
+    let mut shapes = Vec::new();
+    let mut current: Option<Shape> = None;
+    for command in &path.commands {
+        match *command {
+            PathCommand::MoveTo(p) => {
+                shapes.extend(current.take());
+                let mut shape = Shape::default();
+                shape.push(p, origin);
+                current = Some(shape);
+            }
+            PathCommand::LineTo(p) => current.get_or_insert_with(Shape::default).push(p, origin),
+            PathCommand::CubicTo { ctrl1, ctrl2, to } => {
+                let shape = current.get_or_insert_with(Shape::default);
+                if let (Some(&from), Some(out)) = (shape.vertices.last(), shape.out_tangents.last_mut()) {
+                    *out = Point::new(ctrl1.x - from.x, ctrl1.y - from.y);
+                }
+                shape.push(to, Point::new(ctrl2.x - to.x, ctrl2.y - to.y));
+            }
+            PathCommand::Close => {
+                if let Some(mut shape) = current.take() {
+                    shape.closed = true;
+                    shapes.push(shape);
+                }
+            }
+        }
+    }
+    shapes.extend(current);
+    shapes.into_iter().filter(|shape| !shape.vertices.is_empty()).map(Shape::into_json).collect()
+}
+
+fn round(value: f32) -> f64 {
+    (f64::from(value) * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(color: [u8; 4]) -> VectorDocument {
+        let mut path = VectorPath::polygon(&[Point::new(1.0, 1.0), Point::new(9.0, 1.0), Point::new(1.0, 1.0)]);
+        path.commands.insert(
+            2,
+            PathCommand::CubicTo {
+                ctrl1: Point::new(9.0, 3.0),
+                ctrl2: Point::new(7.0, 5.0),
+                to: Point::new(5.0, 5.0),
+            },
+        );
+        VectorDocument {
+            width: 10,
+            height: 6,
+            layers: vec![ColorLayer {
+                palette_index: 0,
+                color,
+                background: false,
+                paths: vec![path],
+            }],
+        }
+    }
+
+    #[test]
+    fn static_image_is_one_frame() {
+        let json = LottieEmitter::default().emit(&document([255, 0, 0, 255]), &VectorizeOptions::default());
+        let lottie: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(lottie["op"], 1);
+        assert_eq!(lottie["w"], 10);
+        let layer = &lottie["layers"][0];
+        assert_eq!(layer["ty"], 4);
+        assert_eq!(layer["nm"], "#ff0000");
+
+        let shape = &layer["shapes"][0]["it"][0]["ks"]["k"];
+        assert_eq!(shape["c"], true);
+        // The closing vertex duplicates the first and is dropped.
+        assert_eq!(shape["v"], json!([[1.0, 1.0], [9.0, 1.0], [5.0, 5.0]]));
+        assert_eq!(shape["o"], json!([[0.0, 0.0], [0.0, 2.0], [0.0, 0.0]]));
+        assert_eq!(shape["i"], json!([[0.0, 0.0], [0.0, 0.0], [2.0, 0.0]]));
+    }
+
+    #[test]
+    fn frames_follow_their_delays() {
+        let frames = [
+            AnimationFrame {
+                document: document([255, 0, 0, 255]),
+                delay_ms: 100,
+            },
+            AnimationFrame {
+                document: document([0, 0, 255, 255]),
+                delay_ms: 500,
+            },
+            // Like browsers (and the SVG emitter), near-zero delays play as 100 ms.
+            AnimationFrame {
+                document: document([0, 255, 0, 255]),
+                delay_ms: 0,
+            },
+        ];
+        let json = LottieEmitter { frame_rate: 10.0 }.emit_frames(&frames, &VectorizeOptions::default());
+        let lottie: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(lottie["op"], 7);
+        let layers = lottie["layers"].as_array().unwrap();
+        assert_eq!(layers.len(), 3);
+        assert_eq!((layers[0]["ip"].clone(), layers[0]["op"].clone()), (json!(0), json!(1)));
+        assert_eq!((layers[1]["ip"].clone(), layers[1]["op"].clone()), (json!(1), json!(6)));
+        assert_eq!((layers[2]["ip"].clone(), layers[2]["op"].clone()), (json!(6), json!(7)));
+        assert_eq!(layers[1]["ind"], 2);
+    }
+}
@@ -0,0 +1,221 @@
+//! Output formats that serialize a [`VectorDocument`](crate::VectorDocument).
+
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Emitter, Point};
+
+mod android;
+mod compose;
+mod dxf;
+mod eps;
+mod gcode;
+mod geojson;
+mod hpgl;
+mod lottie;
+mod pdf;
+mod react;
+mod svg;
+mod swiftui;
+
+pub use android::AndroidVectorEmitter;
+pub use compose::ComposeEmitter;
+pub use dxf::DxfEmitter;
+pub use eps::EpsEmitter;
+pub use gcode::{GcodeEmitter, LaserPass, LaserProfile, LaserSettings};
+pub use geojson::{GeoJsonEmitter, WorldFile};
+pub use hpgl::{HpglEmitter, PenMap};
+pub use lottie::LottieEmitter;
+pub use pdf::PdfEmitter;
+pub use react::{ReactColors, ReactEmitter};
+pub use svg::SvgEmitter;
+pub use swiftui::SwiftUiEmitter;
+
+/// Built-in output formats, selectable by name or file extension.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Svg,
+    Pdf,
+    Eps,
+    Dxf,
+    Hpgl,
+    Gcode,
+    /// Android `VectorDrawable` XML.
+    Android,
+    /// React function component (TSX, or JSX for `.jsx` paths).
+    React,
+    /// SwiftUI `Shape`/`View` source.
+    SwiftUi,
+    /// Jetpack Compose `ImageVector` Kotlin source.
+    Compose,
+    /// Lottie (Bodymovin) JSON animation.
+    Lottie,
+    /// GeoJSON polygons, optionally georeferenced through a world file.
+    GeoJson,
+}
+
+impl OutputFormat {
+    pub const ALL: &'static [OutputFormat] = &[
+        OutputFormat::Svg,
+        OutputFormat::Pdf,
+        OutputFormat::Eps,
+        OutputFormat::Dxf,
+        OutputFormat::Hpgl,
+        OutputFormat::Gcode,
+        OutputFormat::Android,
+        OutputFormat::React,
+        OutputFormat::SwiftUi,
+        OutputFormat::Compose,
+        OutputFormat::Lottie,
+        OutputFormat::GeoJson,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Svg => "svg",
+            OutputFormat::Pdf => "pdf",
+            OutputFormat::Eps => "eps",
+            OutputFormat::Dxf => "dxf",
+            OutputFormat::Hpgl => "hpgl",
+            OutputFormat::Gcode => "gcode",
+            OutputFormat::Android => "android",
+            OutputFormat::React => "react",
+            OutputFormat::SwiftUi => "swiftui",
+            OutputFormat::Compose => "compose",
+            OutputFormat::Lottie => "lottie",
+            OutputFormat::GeoJson => "geojson",
+        }
+    }
+
+    /// File extension (without the dot) conventionally used for this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Hpgl => "plt",
+            OutputFormat::Android => "xml",
+            OutputFormat::React => "tsx",
+            OutputFormat::SwiftUi => "swift",
+            OutputFormat::Compose => "kt",
+            OutputFormat::Lottie => "json",
+            other => other.name(),
+        }
+    }
+
+    /// Guesses the format from a path's extension, e.g. `logo.pdf`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "hpgl" | "hpg" => return Some(OutputFormat::Hpgl),
+            "nc" | "ngc" => return Some(OutputFormat::Gcode),
+            "jsx" => return Some(OutputFormat::React),
+            _ => {}
+        }
+        Self::ALL.iter().copied().find(|format| format.extension() == extension)
+    }
+
+    /// Default emitter for this format.
+    pub fn emitter(self) -> Box<dyn Emitter> {
+        match self {
+            OutputFormat::Svg => Box::new(SvgEmitter),
+            OutputFormat::Pdf => Box::new(PdfEmitter),
+            OutputFormat::Eps => Box::new(EpsEmitter),
+            OutputFormat::Dxf => Box::new(DxfEmitter::default()),
+            OutputFormat::Hpgl => Box::new(HpglEmitter::default()),
+            OutputFormat::Gcode => Box::new(GcodeEmitter::default()),
+            OutputFormat::Android => Box::new(AndroidVectorEmitter),
+            OutputFormat::React => Box::new(ReactEmitter::default()),
+            OutputFormat::SwiftUi => Box::new(SwiftUiEmitter::default()),
+            OutputFormat::Compose => Box::new(ComposeEmitter::default()),
+            OutputFormat::Lottie => Box::new(LottieEmitter::default()),
+            OutputFormat::GeoJson => Box::new(GeoJsonEmitter::default()),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let lower = value.to_ascii_lowercase();
+        Self::ALL.iter().copied().find(|format| format.name() == lower).ok_or_else(|| {
+            let names: Vec<&str> = Self::ALL.iter().map(|format| format.name()).collect();
+            format!("format must be one of: {}", names.join(", "))
+        })
+    }
+}
+
+/// PascalCase type name for generated source, derived from e.g. a file stem
+/// (`company-logo` → `CompanyLogo`). Names that would start with a digit get a `Svg` prefix.
+pub fn type_name_from(stem: &str) -> String {
+    let mut name: String = stem
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().map(|c| c.to_ascii_uppercase()).unwrap_or_default();
+            std::iter::once(first).chain(chars).collect::<String>()
+        })
+        .collect();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name.insert_str(0, "Svg");
+    }
+    name
+}
+
+/// Browsers show GIF frames with a delay of 10 ms or less for 100 ms; animated output matches
+/// that so every format plays at the same speed.
+pub(crate) fn display_delay_ms(delay_ms: u32) -> u64 {
+    if delay_ms <= 10 {
+        100
+    } else {
+        u64::from(delay_ms)
+    }
+}
+
+/// Greedy nearest-neighbour ordering starting from `position`, which is left at the end of the
+/// last polyline.
+pub(crate) fn order_for_travel(mut polylines: Vec<Vec<Point>>, position: &mut Point) -> Vec<Vec<Point>> {
+    polylines.retain(|polyline| polyline.len() > 1);
+    let mut ordered = Vec::with_capacity(polylines.len());
+
+    while !polylines.is_empty() {
+        let mut best = (0, 0, f32::MAX);
+        for (index, polyline) in polylines.iter().enumerate() {
+            let closed = polyline.first() == polyline.last();
+            let candidates = if closed { polyline.len() - 1 } else { 1 };
+            for (vertex, p) in polyline.iter().take(candidates).enumerate() {
+                let distance = (p.x - position.x).powi(2) + (p.y - position.y).powi(2);
+                if distance < best.2 {
+                    best = (index, vertex, distance);
+                }
+            }
+        }
+
+        let mut polyline = polylines.swap_remove(best.0);
+        if best.1 > 0 {
+            // Rotate the closed loop so it starts (and ends) at the nearest vertex.
+            polyline.pop();
+            polyline.rotate_left(best.1);
+            polyline.push(polyline[0]);
+        }
+        *position = *polyline.last().expect("polylines have at least two points");
+        ordered.push(polyline);
+    }
+
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn type_names_are_pascal_case_identifiers() {
+        assert_eq!(type_name_from("company-logo_v2"), "CompanyLogoV2");
+        assert_eq!(type_name_from("2x"), "Svg2x");
+        assert_eq!(type_name_from("--"), "Svg");
+    }
+}
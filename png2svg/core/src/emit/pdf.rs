@@ -0,0 +1,168 @@
+use std::fmt::Write as FmtWrite;
+
+use crate::{opacity_from_options, Emitter, PathCommand, VectorDocument, VectorizeOptions};
+
+/// Single-page PDF 1.4 with the page sized to the image (1 px = 1 pt).
+///
+/// Each palette color is one fill operation over all of its paths, and curves stay cubic Bézier
+/// (`c`) operators. Partial opacity is applied through an `ExtGState` per distinct alpha.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PdfEmitter;
+
+impl Emitter for PdfEmitter {
+    fn emit(&self, document: &VectorDocument, options: &VectorizeOptions) -> String {
+        let mut content = String::new();
+        // Flip the y axis so path coordinates stay in image space (origin top-left).
+        writeln!(content, "1 0 0 -1 0 {} cm", document.height).ok();
+
+        let mut opacities: Vec<f32> = Vec::new();
+        for layer in &document.layers {
+            if layer.paths.is_empty() {
+                continue;
+            }
+            let [r, g, b, a] = layer.color;
+            let opacity = opacity_from_options(a, options);
+
+            content.push_str("q\n");
+            if opacity < 1.0 {
+                let state = match opacities.iter().position(|&known| known == opacity) {
+                    Some(position) => position,
+                    None => {
+                        opacities.push(opacity);
+                        opacities.len() - 1
+                    }
+                };
+                writeln!(content, "/GS{state} gs").ok();
+            }
+            writeln!(content, "{} {} {} rg", channel(r), channel(g), channel(b)).ok();
+
+            for path in &layer.paths {
+                for command in &path.commands {
+                    match *command {
+                        PathCommand::MoveTo(p) => writeln!(content, "{:.2} {:.2} m", p.x, p.y),
+                        PathCommand::LineTo(p) => writeln!(content, "{:.2} {:.2} l", p.x, p.y),
+                        PathCommand::CubicTo { ctrl1, ctrl2, to } => writeln!(
+                            content,
+                            "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c",
+                            ctrl1.x, ctrl1.y, ctrl2.x, ctrl2.y, to.x, to.y
+                        ),
+                        PathCommand::Close => writeln!(content, "h"),
+                    }
+                    .ok();
+                }
+            }
+            // Even-odd so hole subpaths are cut out.
+            content.push_str("f*\nQ\n");
+        }
+
+        let mut resources = String::from("<< ");
+        if !opacities.is_empty() {
+            resources.push_str("/ExtGState << ");
+            for (state, opacity) in opacities.iter().enumerate() {
+                write!(resources, "/GS{state} << /Type /ExtGState /ca {opacity:.3} >> ").ok();
+            }
+            resources.push_str(">> ");
+        }
+        resources.push_str(">>");
+
+        let objects = [
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {w} {h}] /Resources {resources} /Contents 4 0 R >>",
+                w = document.width,
+                h = document.height
+            ),
+            format!("<< /Length {} >>\nstream\n{content}endstream", content.len()),
+        ];
+
+        write_pdf(&objects)
+    }
+}
+
+/// Serializes numbered objects (`1 0 obj` onwards) with a cross-reference table and trailer.
+fn write_pdf(objects: &[String]) -> String {
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        write!(pdf, "{} 0 obj\n{object}\nendobj\n", i + 1).ok();
+    }
+
+    let xref_offset = pdf.len();
+    write!(pdf, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).ok();
+    for offset in offsets {
+        writeln!(pdf, "{offset:010} 00000 n ").ok();
+    }
+    write!(
+        pdf,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref_offset}\n%%EOF\n",
+        objects.len() + 1
+    )
+    .ok();
+    pdf
+}
+
+fn channel(value: u8) -> String {
+    let scaled = value as f32 / 255.0;
+    let formatted = format!("{scaled:.4}");
+    formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColorLayer, Point, VectorPath};
+
+    fn sample_document() -> VectorDocument {
+        let mut path = VectorPath::polygon(&[Point::new(1.0, 1.0), Point::new(9.0, 1.0), Point::new(9.0, 9.0)]);
+        path.commands.insert(
+            2,
+            PathCommand::CubicTo {
+                ctrl1: Point::new(9.0, 3.0),
+                ctrl2: Point::new(9.0, 5.0),
+                to: Point::new(9.0, 7.0),
+            },
+        );
+        VectorDocument {
+            width: 10,
+            height: 12,
+            layers: vec![ColorLayer {
+                palette_index: 0,
+                color: [255, 0, 51, 128],
+                background: false,
+                paths: vec![path],
+            }],
+        }
+    }
+
+    #[test]
+    fn writes_valid_cross_reference_table() {
+        let pdf = PdfEmitter.emit(&sample_document(), &VectorizeOptions::default());
+        assert!(pdf.starts_with("%PDF-1.4"));
+        assert!(pdf.contains("/MediaBox [0 0 10 12]"));
+
+        let startxref = pdf.rsplit("startxref\n").next().unwrap();
+        let xref_offset: usize = startxref.lines().next().unwrap().parse().unwrap();
+        assert!(pdf[xref_offset..].starts_with("xref"));
+
+        let entries: Vec<usize> = pdf[xref_offset..]
+            .lines()
+            .skip(3)
+            .take(4)
+            .map(|line| line[..10].parse().unwrap())
+            .collect();
+        for (i, offset) in entries.iter().enumerate() {
+            assert!(pdf[*offset..].starts_with(&format!("{} 0 obj", i + 1)));
+        }
+    }
+
+    #[test]
+    fn keeps_curves_and_colors() {
+        let pdf = PdfEmitter.emit(&sample_document(), &VectorizeOptions::default());
+        assert!(pdf.contains("1 0 0.2 rg"));
+        assert!(pdf.contains("9.00 3.00 9.00 5.00 9.00 7.00 c"));
+        assert!(pdf.contains("/GS0 gs"));
+        assert!(pdf.contains("1 0 0 -1 0 12 cm"));
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt::Write as FmtWrite;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use super::svg::path_data;
+use crate::{opacity_from_options, to_hex, Emitter, VectorDocument, VectorizeOptions};
+
+/// How a generated component colors its paths.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReactColors {
+    /// Palette colors are written inline.
+    #[default]
+    Fixed,
+    /// Each palette color becomes an optional `colorN` prop defaulting to the traced color.
+    Props,
+    /// Every path uses `currentColor`, for single-color icons styled through CSS `color`.
+    Current,
+}
+
+impl FromStr for ReactColors {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "fixed" => Ok(ReactColors::Fixed),
+            "props" => Ok(ReactColors::Props),
+            "current" | "currentcolor" => Ok(ReactColors::Current),
+            _ => Err("react colors must be one of: fixed, props, current".into()),
+        }
+    }
+}
+
+/// React function component (`.tsx`, or `.jsx` when `typescript` is off) rendering the SVG.
+///
+/// Attributes are camelCased for JSX and remaining props are spread onto `<svg>`, so callers
+/// can pass `className`, `width`, event handlers and so on.
+#[derive(Debug, Clone)]
+pub struct ReactEmitter {
+    pub component_name: String,
+    pub typescript: bool,
+    pub colors: ReactColors,
+}
+
+impl Default for ReactEmitter {
+    fn default() -> Self {
+        Self {
+            component_name: "VectorImage".to_string(),
+            typescript: true,
+            colors: ReactColors::default(),
+        }
+    }
+}
+
+impl Emitter for ReactEmitter {
+    fn emit(&self, document: &VectorDocument, options: &VectorizeOptions) -> String {
+        let name = &self.component_name;
+        let layers: Vec<_> = document.layers.iter().filter(|layer| !layer.paths.is_empty()).collect();
+        let color_props: Vec<(String, String)> = match self.colors {
+            ReactColors::Props => layers
+                .iter()
+                .enumerate()
+                .map(|(i, layer)| (format!("color{}", i + 1), format!("#{}", to_hex(layer.color))))
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let mut code = String::new();
+        if self.typescript {
+            code.push_str("import type { SVGProps } from \"react\";\n\n");
+            if color_props.is_empty() {
+                writeln!(code, "export type {name}Props = SVGProps<SVGSVGElement>;\n").ok();
+            } else {
+                writeln!(code, "export interface {name}Props extends SVGProps<SVGSVGElement> {{").ok();
+                for (prop, _) in &color_props {
+                    writeln!(code, "  {prop}?: string;").ok();
+                }
+                code.push_str("}\n\n");
+            }
+        }
+
+        let mut params: Vec<String> = color_props
+            .iter()
+            .map(|(prop, default)| format!("{prop} = \"{default}\""))
+            .collect();
+        params.push("...props".to_string());
+        let annotation = if self.typescript { format!(": {name}Props") } else { String::new() };
+        writeln!(code, "export default function {name}({{ {} }}{annotation}) {{", params.join(", ")).ok();
+        code.push_str("  return (\n");
+        writeln!(
+            code,
+            "    <svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {} {}\" {{...props}}>",
+            document.width, document.height
+        )
+        .ok();
+
+        for (i, layer) in layers.iter().enumerate() {
+            let fill = match self.colors {
+                ReactColors::Fixed => format!("\"#{}\"", to_hex(layer.color)),
+                ReactColors::Props => format!("{{{}}}", color_props[i].0),
+                ReactColors::Current => "\"currentColor\"".to_string(),
+            };
+            let opacity = opacity_from_options(layer.color[3], options);
+            let fill_opacity = if opacity < 1.0 {
+                format!(" fillOpacity={{{opacity:.3}}}")
+            } else {
+                String::new()
+            };
+            writeln!(code, "      <g fill={fill}{fill_opacity}>").ok();
+            for path in &layer.paths {
+                let fill_rule = if path.has_holes() { " fillRule=\"evenodd\"" } else { "" };
+                writeln!(code, "        <path d=\"{}\"{fill_rule} />", path_data(path)).ok();
+            }
+            code.push_str("      </g>\n");
+        }
+
+        code.push_str("    </svg>\n  );\n}\n");
+        code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emit::type_name_from;
+    use crate::{ColorLayer, Point, VectorPath};
+
+    fn document() -> VectorDocument {
+        let triangle = VectorPath::polygon(&[Point::new(0.0, 0.0), Point::new(4.0, 0.0), Point::new(0.0, 4.0)]);
+        VectorDocument {
+            width: 4,
+            height: 4,
+            layers: vec![
+                ColorLayer {
+                    palette_index: 0,
+                    color: [255, 0, 0, 255],
+                    background: false,
+                    paths: vec![triangle.clone()],
+                },
+                ColorLayer {
+                    palette_index: 1,
+                    color: [0, 0, 255, 255],
+                    background: false,
+                    paths: vec![triangle],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn color_props_default_to_palette() {
+        let emitter = ReactEmitter {
+            component_name: "Logo".to_string(),
+            colors: ReactColors::Props,
+            ..ReactEmitter::default()
+        };
+        let tsx = emitter.emit(&document(), &VectorizeOptions::default());
+        assert!(tsx.contains("export interface LogoProps extends SVGProps<SVGSVGElement> {"));
+        assert!(tsx.contains("color2?: string;"));
+        assert!(tsx.contains("function Logo({ color1 = \"#ff0000\", color2 = \"#0000ff\", ...props }: LogoProps)"));
+        assert!(tsx.contains("<g fill={color1}>"));
+        assert!(tsx.contains("viewBox=\"0 0 4 4\" {...props}>"));
+    }
+
+    #[test]
+    fn jsx_with_current_color() {
+        let emitter = ReactEmitter {
+            component_name: type_name_from("company-logo_v2"),
+            typescript: false,
+            colors: ReactColors::Current,
+        };
+        let jsx = emitter.emit(&document(), &VectorizeOptions::default());
+        assert!(jsx.starts_with("export default function CompanyLogoV2({ ...props }) {"));
+        assert_eq!(jsx.matches("fill=\"currentColor\"").count(), 2);
+        assert!(!jsx.contains("SVGProps"));
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::fmt::Write as FmtWrite;
+
+use crate::emit::display_delay_ms;
+use crate::{
+    opacity_from_options, to_hex, AnimationFrame, Emitter, PathCommand, VectorDocument, VectorPath, VectorizeOptions,
+};
+
+/// SVG 1.1 output with one `<g>` per palette color; the default emitter.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SvgEmitter;
+
+impl Emitter for SvgEmitter {
+    fn emit(&self, document: &VectorDocument, options: &VectorizeOptions) -> String {
+        let mut svg = String::with_capacity(document.width as usize * document.height as usize / 10);
+        writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {w} {h}\" aria-label=\"vectorized\">",
+            w = document.width,
+            h = document.height
+        )
+        .ok();
+
+        // Output paths grouped by color
+        for layer in &document.layers {
+            let opacity = opacity_from_options(layer.color[3], options);
+            let hex = to_hex(layer.color);
+
+            writeln!(
+                svg,
+                "  <g fill=\"#{hex}\" fill-opacity=\"{opacity:.3}\">",
+                hex = hex,
+                opacity = opacity
+            )
+            .ok();
+
+            for path in &layer.paths {
+                // A kept background fills the whole canvas; write it as a plain <rect>.
+                if layer.background {
+                    writeln!(svg, "    <rect width=\"{}\" height=\"{}\"/>", document.width, document.height).ok();
+                    continue;
+                }
+                let fill_rule = if path.has_holes() { " fill-rule=\"evenodd\"" } else { "" };
+                writeln!(svg, "    <path d=\"{}\"{fill_rule}/>", path_data(path)).ok();
+            }
+
+            writeln!(svg, "  </g>").ok();
+        }
+
+        svg.push_str("</svg>");
+        svg
+    }
+
+    /// Animated SVG that switches between frames with CSS keyframes, looping forever.
+    ///
+    /// A path that is identical in several frames is written once and only toggled, and paths
+    /// present in every frame are not animated at all. A single frame gives a static SVG.
+    fn emit_frames(&self, frames: &[AnimationFrame], options: &VectorizeOptions) -> String {
+        let (first, animated) = match frames {
+            [] => return self.emit(&VectorDocument::new(0, 0), options),
+            [only] => return self.emit(&only.document, options),
+            [first, ..] => (first, frames),
+        };
+
+        struct Shape {
+            data: String,
+            holes: bool,
+            frames: Vec<bool>,
+        }
+        struct Layer {
+            color: [u8; 4],
+            background: bool,
+            shapes: Vec<Shape>,
+            by_data: HashMap<String, usize>,
+        }
+        // Distinct paths per palette entry, stacked in the order the documents give (a kept
+        // background comes first, although its palette index is the highest).
+        let mut layers: Vec<Layer> = Vec::new();
+        let mut layer_of: HashMap<usize, usize> = HashMap::new();
+        for (i, frame) in animated.iter().enumerate() {
+            for layer in &frame.document.layers {
+                let index = *layer_of.entry(layer.palette_index).or_insert_with(|| {
+                    layers.push(Layer {
+                        color: layer.color,
+                        background: layer.background,
+                        shapes: Vec::new(),
+                        by_data: HashMap::new(),
+                    });
+                    layers.len() - 1
+                });
+                let Layer { shapes, by_data, .. } = &mut layers[index];
+                for path in &layer.paths {
+                    let data = path_data(path);
+                    let index = *by_data.entry(data.clone()).or_insert_with(|| {
+                        shapes.push(Shape {
+                            data,
+                            holes: path.has_holes(),
+                            frames: vec![false; animated.len()],
+                        });
+                        shapes.len() - 1
+                    });
+                    shapes[index].frames[i] = true;
+                }
+            }
+        }
+
+        let delays: Vec<u64> = animated.iter().map(|frame| display_delay_ms(frame.delay_ms)).collect();
+        let total: u64 = delays.iter().sum();
+        let mut starts = Vec::with_capacity(delays.len());
+        let mut elapsed = 0;
+        for delay in &delays {
+            starts.push(elapsed as f64 * 100.0 / total as f64);
+            elapsed += delay;
+        }
+
+        // One keyframes rule per distinct visibility pattern.
+        let mut patterns: Vec<&[bool]> = Vec::new();
+        for layer in &layers {
+            for shape in &layer.shapes {
+                if shape.frames.contains(&false) && !patterns.contains(&shape.frames.as_slice()) {
+                    patterns.push(&shape.frames);
+                }
+            }
+        }
+        let mut style = String::new();
+        for (n, pattern) in patterns.iter().enumerate() {
+            let hidden = if pattern[0] { "" } else { "visibility:hidden;" };
+            writeln!(style, "    .f{n}{{{hidden}animation:f{n} {total}ms step-end infinite}}").ok();
+            write!(style, "    @keyframes f{n}{{").ok();
+            let mut previous = None;
+            for (&visible, start) in pattern.iter().zip(&starts) {
+                if previous != Some(visible) {
+                    write!(style, "{start:.3}%{{visibility:{}}}", visibility(visible)).ok();
+                    previous = Some(visible);
+                }
+            }
+            writeln!(style, "100%{{visibility:{}}}}}", visibility(pattern[pattern.len() - 1])).ok();
+        }
+
+        let document = &first.document;
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {w} {h}\" aria-label=\"vectorized\">",
+            w = document.width,
+            h = document.height
+        )
+        .ok();
+        writeln!(svg, "  <style>\n{style}  </style>").ok();
+
+        for layer in &layers {
+            let opacity = opacity_from_options(layer.color[3], options);
+            writeln!(svg, "  <g fill=\"#{}\" fill-opacity=\"{opacity:.3}\">", to_hex(layer.color)).ok();
+            for shape in &layer.shapes {
+                let class = match patterns.iter().position(|pattern| *pattern == shape.frames.as_slice()) {
+                    Some(n) => format!(" class=\"f{n}\""),
+                    None => String::new(),
+                };
+                if layer.background {
+                    writeln!(svg, "    <rect{class} width=\"{}\" height=\"{}\"/>", document.width, document.height).ok();
+                    continue;
+                }
+                let fill_rule = if shape.holes { " fill-rule=\"evenodd\"" } else { "" };
+                writeln!(svg, "    <path{class} d=\"{}\"{fill_rule}/>", shape.data).ok();
+            }
+            writeln!(svg, "  </g>").ok();
+        }
+
+        svg.push_str("</svg>");
+        svg
+    }
+}
+
+fn visibility(visible: bool) -> &'static str {
+    if visible {
+        "visible"
+    } else {
+        "hidden"
+    }
+}
+
+/// SVG `d` attribute for a path.
+pub(crate) fn path_data(path: &VectorPath) -> String {
+    let mut d = String::new();
+    for command in &path.commands {
+        if !d.is_empty() {
+            d.push(' ');
+        }
+        match *command {
+            PathCommand::MoveTo(p) => write!(d, "M {:.2} {:.2}", p.x, p.y),
+            PathCommand::LineTo(p) => write!(d, "L {:.2} {:.2}", p.x, p.y),
+            PathCommand::CubicTo { ctrl1, ctrl2, to } => write!(
+                d,
+                "C {:.2} {:.2} {:.2} {:.2} {:.2} {:.2}",
+                ctrl1.x, ctrl1.y, ctrl2.x, ctrl2.y, to.x, to.y
+            ),
+            PathCommand::Close => write!(d, "Z"),
+        }
+        .ok();
+    }
+    d
+}
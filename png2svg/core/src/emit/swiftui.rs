@@ -0,0 +1,137 @@
+use std::fmt::Write as FmtWrite;
+
+use crate::{opacity_from_options, Emitter, PathCommand, Point, VectorDocument, VectorizeOptions};
+
+/// SwiftUI source: one `Shape` per palette color plus a `View` stacking them in their colors.
+///
+/// Shapes are built in image coordinates and scaled to the proposed rect, so they can also be
+/// used on their own (e.g. as masks). Colors with holes are filled with `eoFill`.
+#[derive(Debug, Clone)]
+pub struct SwiftUiEmitter {
+    pub type_name: String,
+}
+
+impl Default for SwiftUiEmitter {
+    fn default() -> Self {
+        Self {
+            type_name: "VectorImage".to_string(),
+        }
+    }
+}
+
+impl Emitter for SwiftUiEmitter {
+    fn emit(&self, document: &VectorDocument, options: &VectorizeOptions) -> String {
+        let name = &self.type_name;
+        let (width, height) = (document.width, document.height);
+        let layers: Vec<_> = document.layers.iter().filter(|layer| !layer.paths.is_empty()).collect();
+
+        let mut swift = String::from("import SwiftUI\n\n");
+        writeln!(swift, "struct {name}: View {{").ok();
+        swift.push_str("    var body: some View {\n        ZStack {\n");
+        for (i, layer) in layers.iter().enumerate() {
+            let [r, g, b, a] = layer.color;
+            let opacity = opacity_from_options(a, options);
+            let even_odd = layer.paths.iter().any(|path| path.has_holes());
+            writeln!(
+                swift,
+                "            {name}Layer{}().fill(Color(.sRGB, red: {}, green: {}, blue: {}, opacity: {}), style: FillStyle(eoFill: {even_odd}))",
+                i + 1,
+                channel(r),
+                channel(g),
+                channel(b),
+                number(opacity)
+            )
+            .ok();
+        }
+        swift.push_str("        }\n");
+        writeln!(swift, "        .aspectRatio(CGSize(width: {width}, height: {height}), contentMode: .fit)").ok();
+        swift.push_str("    }\n}\n");
+
+        for (i, layer) in layers.iter().enumerate() {
+            writeln!(swift, "\nstruct {name}Layer{}: Shape {{", i + 1).ok();
+            swift.push_str("    func path(in rect: CGRect) -> Path {\n        var path = Path()\n");
+            for path in &layer.paths {
+                for command in &path.commands {
+                    match *command {
+                        PathCommand::MoveTo(p) => writeln!(swift, "        path.move(to: {})", point(p)),
+                        PathCommand::LineTo(p) => writeln!(swift, "        path.addLine(to: {})", point(p)),
+                        PathCommand::CubicTo { ctrl1, ctrl2, to } => writeln!(
+                            swift,
+                            "        path.addCurve(to: {}, control1: {}, control2: {})",
+                            point(to),
+                            point(ctrl1),
+                            point(ctrl2)
+                        ),
+                        PathCommand::Close => writeln!(swift, "        path.closeSubpath()"),
+                    }
+                    .ok();
+                }
+            }
+            writeln!(
+                swift,
+                "        return path.applying(CGAffineTransform(scaleX: rect.width / {width}, y: rect.height / {height}))"
+            )
+            .ok();
+            swift.push_str("    }\n}\n");
+        }
+
+        swift
+    }
+}
+
+fn point(p: Point) -> String {
+    format!("CGPoint(x: {:.2}, y: {:.2})", p.x, p.y)
+}
+
+fn channel(value: u8) -> String {
+    number(value as f32 / 255.0)
+}
+
+fn number(value: f32) -> String {
+    let formatted = format!("{value:.3}");
+    formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColorLayer, VectorPath};
+
+    #[test]
+    fn writes_view_and_shapes() {
+        let mut path = VectorPath::polygon(&[Point::new(1.0, 1.0), Point::new(9.0, 1.0), Point::new(9.0, 9.0)]);
+        path.commands.insert(
+            2,
+            PathCommand::CubicTo {
+                ctrl1: Point::new(9.0, 3.0),
+                ctrl2: Point::new(9.0, 5.0),
+                to: Point::new(9.0, 7.0),
+            },
+        );
+        let document = VectorDocument {
+            width: 10,
+            height: 12,
+            layers: vec![ColorLayer {
+                palette_index: 0,
+                color: [255, 0, 51, 255],
+                background: false,
+                paths: vec![path],
+            }],
+        };
+        let emitter = SwiftUiEmitter {
+            type_name: "Badge".to_string(),
+        };
+        let swift = emitter.emit(&document, &VectorizeOptions::default());
+
+        assert!(swift.contains("struct Badge: View {"));
+        assert!(swift.contains(
+            "BadgeLayer1().fill(Color(.sRGB, red: 1, green: 0, blue: 0.2, opacity: 1), style: FillStyle(eoFill: false))"
+        ));
+        assert!(swift.contains("struct BadgeLayer1: Shape {"));
+        assert!(swift.contains(
+            "path.addCurve(to: CGPoint(x: 9.00, y: 7.00), control1: CGPoint(x: 9.00, y: 3.00), control2: CGPoint(x: 9.00, y: 5.00))"
+        ));
+        assert!(swift.contains("path.closeSubpath()"));
+        assert!(swift.contains("scaleX: rect.width / 10, y: rect.height / 12"));
+    }
+}
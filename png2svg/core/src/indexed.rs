@@ -0,0 +1,262 @@
+use std::collections::HashSet;
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageFormat, RgbaImage};
+
+use crate::background::remove_palette_background;
+use crate::color::SourceEncoding;
+use crate::{palette_size_from_options, QuantizedImage, VectorizeError, VectorizeOptions};
+
+/// Pixel values of an indexed or grayscale PNG, read before any expansion to RGBA.
+pub(crate) struct RawIndices {
+    pub width: u32,
+    pub height: u32,
+    pub values: Vec<u32>,
+    pub bit_depth: u8,
+    /// `PLTE` colors with `tRNS` alpha applied; `None` for grayscale images.
+    pub palette: Option<Vec<[u8; 4]>>,
+    pub icc_profile: Option<Vec<u8>>,
+}
+
+/// Reads palette indices or gray levels straight from the PNG data. Returns `None` for color
+/// PNGs and for APNGs, whose frames are decoded separately.
+pub(crate) fn read_png_indices(bytes: &[u8]) -> Result<Option<RawIndices>, VectorizeError> {
+    let invalid = |err: png::DecodingError| VectorizeError::Vectorize(format!("invalid PNG: {err}"));
+    let mut decoder = png::Decoder::new(Cursor::new(bytes));
+    decoder.set_transformations(png::Transformations::IDENTITY);
+    let mut reader = decoder.read_info().map_err(invalid)?;
+    let info = reader.info();
+    if !matches!(info.color_type, png::ColorType::Indexed | png::ColorType::Grayscale) || info.animation_control.is_some() {
+        return Ok(None);
+    }
+    let palette = info.palette.as_ref().map(|palette| {
+        let alpha = info.trns.as_deref().unwrap_or_default();
+        palette
+            .chunks_exact(3)
+            .enumerate()
+            .map(|(i, rgb)| [rgb[0], rgb[1], rgb[2], alpha.get(i).copied().unwrap_or(255)])
+            .collect()
+    });
+    let icc_profile = info.icc_profile.as_ref().map(|icc| icc.to_vec());
+    let size = reader
+        .output_buffer_size()
+        .ok_or_else(|| VectorizeError::Vectorize("image is too large".into()))?;
+    let mut buffer = vec![0; size];
+    let frame = reader.next_frame(&mut buffer).map_err(invalid)?;
+
+    let bits = frame.bit_depth as usize;
+    let mut values = Vec::with_capacity(frame.width as usize * frame.height as usize);
+    for row in buffer.chunks(frame.line_size).take(frame.height as usize) {
+        for x in 0..frame.width as usize {
+            let value = match bits {
+                16 => u32::from(u16::from_be_bytes([row[2 * x], row[2 * x + 1]])),
+                8 => u32::from(row[x]),
+                _ => {
+                    let bit = x * bits;
+                    let shift = 8 - bits - bit % 8;
+                    u32::from(row[bit / 8] >> shift) & ((1 << bits) - 1)
+                }
+            };
+            values.push(value);
+        }
+    }
+
+    Ok(Some(RawIndices {
+        width: frame.width,
+        height: frame.height,
+        values,
+        bit_depth: bits as u8,
+        palette,
+        icc_profile,
+    }))
+}
+
+/// The embedded palette and per-pixel indices of a paletted PNG or single-frame GIF, as a
+/// ready-made quantization. Returns `None` for any other image.
+///
+/// PNG palettes are converted to sRGB like decoded pixels. Fully transparent palette entries
+/// become `[0, 0, 0, 0]`, like the quantizer's transparent slot. Animated GIFs return `None`;
+/// their frames go through [`decode_frames`](crate::decode_frames).
+pub fn decode_indexed(bytes: &[u8]) -> Result<Option<QuantizedImage>, VectorizeError> {
+    let quantized = match image::guess_format(bytes)? {
+        ImageFormat::Png => read_png_indices(bytes)?.and_then(|raw| {
+            let mut palette = raw.palette?;
+            if let Some(encoding) = SourceEncoding::detect(bytes, Some(ImageFormat::Png), raw.icc_profile) {
+                palette = palette_to_srgb(palette, &encoding);
+            }
+            let indices = raw.values.into_iter().map(|value| value as usize).collect();
+            Some(indexed_image(palette, indices, raw.width, raw.height))
+        }),
+        ImageFormat::Gif => read_gif_indices(bytes)?,
+        _ => None,
+    };
+    Ok(quantized)
+}
+
+/// Converts the entries the same way [`load_srgb`](crate::color::load_srgb) converts pixels, so
+/// a color-managed paletted PNG comes out like the same image saved as RGBA.
+fn palette_to_srgb(palette: Vec<[u8; 4]>, encoding: &SourceEncoding) -> Vec<[u8; 4]> {
+    let raw = palette.into_iter().flatten().collect::<Vec<u8>>();
+    let strip = RgbaImage::from_raw((raw.len() / 4) as u32, 1, raw).expect("four channels per entry");
+    let converted = encoding.to_srgb(DynamicImage::ImageRgba8(strip)).to_rgba8();
+    converted.pixels().map(|pixel| pixel.0).collect()
+}
+
+fn read_gif_indices(bytes: &[u8]) -> Result<Option<QuantizedImage>, VectorizeError> {
+    let invalid = |err: gif::DecodingError| VectorizeError::Vectorize(format!("invalid GIF: {err}"));
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options.read_info(Cursor::new(bytes)).map_err(invalid)?;
+    let (width, height) = (u32::from(decoder.width()), u32::from(decoder.height()));
+    let global = decoder.global_palette().map(<[u8]>::to_vec);
+
+    let Some(frame) = decoder.read_next_frame().map_err(invalid)? else {
+        return Ok(None);
+    };
+    let Some(colors) = frame.palette.clone().or(global) else {
+        return Ok(None);
+    };
+    let mut palette: Vec<[u8; 4]> = colors.chunks_exact(3).map(|rgb| [rgb[0], rgb[1], rgb[2], 255]).collect();
+    if let Some(transparent) = frame.transparent.map(usize::from) {
+        if transparent >= palette.len() {
+            palette.resize(transparent + 1, [0, 0, 0, 255]);
+        }
+        palette[transparent] = [0, 0, 0, 0];
+    }
+
+    // Canvas not covered by the frame is transparent.
+    let mut blank = frame.transparent.map(usize::from);
+    let covers_canvas = frame.left == 0
+        && frame.top == 0
+        && u32::from(frame.width) == width
+        && u32::from(frame.height) == height;
+    if !covers_canvas && blank.is_none() {
+        blank = Some(palette.len());
+        palette.push([0, 0, 0, 0]);
+    }
+    let mut indices = vec![blank.unwrap_or(0); width as usize * height as usize];
+    let (left, top, frame_width) = (usize::from(frame.left), usize::from(frame.top), usize::from(frame.width));
+    for (i, &index) in frame.buffer.iter().enumerate() {
+        let (x, y) = (left + i % frame_width.max(1), top + i / frame_width.max(1));
+        if x < width as usize && y < height as usize {
+            indices[y * width as usize + x] = usize::from(index);
+        }
+    }
+
+    if decoder.read_next_frame().map_err(invalid)?.is_some() {
+        return Ok(None);
+    }
+    Ok(Some(indexed_image(palette, indices, width, height)))
+}
+
+fn indexed_image(mut palette: Vec<[u8; 4]>, indices: Vec<usize>, width: u32, height: u32) -> QuantizedImage {
+    // Out-of-range indices in a damaged file render as transparent.
+    let used = indices.iter().max().map_or(0, |&max| max + 1);
+    if palette.len() < used {
+        palette.resize(used, [0, 0, 0, 0]);
+    }
+    for color in &mut palette {
+        if color[3] == 0 {
+            *color = [0, 0, 0, 0];
+        }
+    }
+    QuantizedImage {
+        palette,
+        indices,
+        width,
+        height,
+        background: None,
+    }
+}
+
+/// The embedded palette ready to stand in for quantization, with entries at or below the alpha
+/// threshold (and background entries) made transparent. `None` if it uses more visible colors
+/// than the options ask for, so `colors`/`detail` still cap dithered or photographic inputs.
+pub(crate) fn fit_embedded_palette(quantized: &QuantizedImage, options: &VectorizeOptions) -> Option<QuantizedImage> {
+    let mut fitted = quantized.clone();
+    for color in &mut fitted.palette {
+        if color[3] <= options.alpha_threshold {
+            *color = [0, 0, 0, 0];
+        }
+    }
+    if let Some(background) = &options.background {
+        // Un-matting changes pixel colors, which only quantization can represent.
+        if background.unmatte {
+            return None;
+        }
+        let color = remove_palette_background(&mut fitted, background);
+        fitted.background = color.filter(|_| background.keep_rect);
+    }
+    let visible: HashSet<usize> = fitted
+        .indices
+        .iter()
+        .copied()
+        .filter(|&index| fitted.palette[index][3] > 0)
+        .collect();
+    (visible.len() <= palette_size_from_options(options)).then_some(fitted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn png_palette_is_used_as_is() {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, 3, 2);
+            encoder.set_color(png::ColorType::Indexed);
+            encoder.set_depth(png::BitDepth::Eight);
+            // Two reds that median cut would be free to merge, plus a transparent entry.
+            encoder.set_palette(vec![200, 0, 0, 201, 0, 0, 9, 9, 9]);
+            encoder.set_trns(vec![255, 255, 0]);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[0, 1, 2, 1, 1, 0]).unwrap();
+        }
+        let quantized = decode_indexed(&bytes).unwrap().unwrap();
+        assert_eq!(quantized.palette, [[200, 0, 0, 255], [201, 0, 0, 255], [0, 0, 0, 0]]);
+        assert_eq!(quantized.indices, [0, 1, 2, 1, 1, 0]);
+
+        let options = VectorizeOptions::default();
+        assert!(fit_embedded_palette(&quantized, &options).is_some());
+        let svg = crate::Vectorizer::from_png_bytes(&bytes, options).unwrap().render();
+        assert!(svg.contains("c90000"));
+    }
+
+    #[test]
+    fn png_palette_is_color_managed() {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+            encoder.set_color(png::ColorType::Indexed);
+            encoder.set_source_gamma(png::ScaledFloat::new(1.0));
+            encoder.set_palette(vec![128, 128, 128, 0, 0, 0]);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[0, 1]).unwrap();
+        }
+        // Half linear light is about 188 in sRGB, as in the RGBA decode of the same file.
+        let quantized = decode_indexed(&bytes).unwrap().unwrap();
+        assert_eq!(quantized.palette, [[188, 188, 188, 255], [0, 0, 0, 255]]);
+        assert_eq!(crate::decode_image(&bytes).unwrap().to_rgba8().get_pixel(0, 0).0, [188, 188, 188, 255]);
+    }
+
+    #[test]
+    fn gif_frame_is_placed_on_the_canvas() {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = gif::Encoder::new(&mut bytes, 4, 2, &[0, 0, 255, 255, 255, 0]).unwrap();
+            let frame = gif::Frame {
+                left: 1,
+                width: 2,
+                height: 1,
+                buffer: vec![1, 0].into(),
+                ..gif::Frame::default()
+            };
+            encoder.write_frame(&frame).unwrap();
+        }
+        let quantized = decode_indexed(&bytes).unwrap().unwrap();
+        assert_eq!(quantized.palette.len(), 3);
+        assert_eq!(quantized.palette[2], [0, 0, 0, 0]);
+        assert_eq!(quantized.indices, [2, 1, 0, 2, 2, 2, 2, 2]);
+    }
+}
@@ -1,34 +1,93 @@
 use std::fmt::Write as FmtWrite;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
+use std::sync::OnceLock;
 
-use image::{Rgba, RgbaImage};
+use image::{DynamicImage, GrayImage, ImageBuffer, Rgba, RgbImage, RgbaImage};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::color::{linear_to_srgb16, load_srgb, srgb_to_linear16};
+
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+/// Opens a `tracing` span named after a pipeline stage for the rest of the enclosing block.
+/// Compiles to nothing without the `tracing` feature.
+macro_rules! stage_span {
+    ($name:literal) => {
+        #[cfg(feature = "tracing")]
+        let _stage_span = tracing::info_span!($name).entered();
+    };
+}
+
+/// Records counts for the current stage as a `tracing` event, e.g. `stage_event!(components = n)`.
+macro_rules! stage_event {
+    ($($field:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::info!($($field)+);
+    };
+}
+
+mod animation;
+mod background;
+mod classify;
+mod color;
+mod debug;
+mod document;
+pub mod emit;
+mod indexed;
+mod mask;
+mod optimize;
+mod pipeline;
+mod vectorizer;
+
+pub use animation::{decode_animation, decode_frames, AnimationFrame, Frame};
+pub use background::BackgroundOptions;
+pub use classify::{classify_image, Classification, ImageKind, ImageMetrics};
+pub use debug::DebugArtifacts;
+pub use document::{ColorLayer, PathCommand, VectorDocument, VectorPath};
+pub use emit::{
+    AndroidVectorEmitter, ComposeEmitter, DxfEmitter, EpsEmitter, GcodeEmitter, GeoJsonEmitter, HpglEmitter, LaserPass, LaserProfile,
+    LaserSettings, LottieEmitter, OutputFormat, PdfEmitter, PenMap, ReactColors, ReactEmitter, SvgEmitter, SwiftUiEmitter,
+    WorldFile,
+};
+pub use indexed::decode_indexed;
+pub(crate) use indexed::fit_embedded_palette;
+pub use mask::{mask_to_coco, CocoOptions, LabelMask};
+pub use optimize::{optimize, optimize_with, reconstruction_error, Objective, OptimizeTarget, Optimized, OutputMetrics};
+pub use pipeline::{
+    BoundaryTracer, Emitter, MedianCutQuantizer, Pipeline, PipelineBuilder, Quantizer, RdpSimplifier, Simplifier, Tracer,
+};
+pub use vectorizer::Vectorizer;
+
 #[derive(Debug, Error)]
 pub enum VectorizeError {
     #[error("failed to decode image: {0}")]
     Decode(#[from] image::ImageError),
+    #[error("pixel buffer holds {actual} bytes, expected {expected} for a {width}x{height} image")]
+    BufferSize {
+        width: u32,
+        height: u32,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("failed to encode image: {0}")]
+    Encode(image::ImageError),
     #[error("vectorization failed: {0}")]
     Vectorize(String),
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum VectorizeMode {
+    #[default]
     Logo,
     Poster,
     #[serde(rename = "pixel", alias = "pixelart", alias = "pixel-art")]
     PixelArt,
-}
-
-impl Default for VectorizeMode {
-    fn default() -> Self {
-        Self::Logo
-    }
+    /// Picks one of the other modes, and the tuning options, from the image itself; see
+    /// [`classify_image`]. Stages run without an image treat it as `Logo`.
+    Auto,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,6 +98,11 @@ pub struct VectorizeOptions {
     pub smoothness: f32,
     pub tolerance: f32,
     pub mode: VectorizeMode,
+    /// Pixels with alpha at or below this (0-255) count as transparent and are not traced.
+    /// Anything above keeps its alpha, which is quantized along with the color.
+    pub alpha_threshold: u8,
+    /// Background to remove before tracing; `None` traces it like any other color.
+    pub background: Option<BackgroundOptions>,
 }
 
 impl Default for VectorizeOptions {
@@ -49,31 +113,126 @@ impl Default for VectorizeOptions {
             smoothness: 0.5,
             tolerance: 1.5,
             mode: VectorizeMode::Logo,
+            alpha_threshold: 0,
+            background: None,
         }
     }
 }
 
+/// Vectorizes an encoded image. Animated GIF/APNG input becomes an animated SVG.
 pub fn png_to_svg(png_bytes: &[u8], options: &VectorizeOptions) -> Result<String, VectorizeError> {
-    let image = image::load_from_memory(png_bytes)?;
-    let rgba = image.to_rgba8();
+    Pipeline::default().run_bytes(png_bytes, options)
+}
+
+pub(crate) fn decode_image(bytes: &[u8]) -> Result<DynamicImage, VectorizeError> {
+    stage_span!("decode");
+    let image = load_srgb(bytes)?;
+    stage_event!(bytes = bytes.len(), width = image.width(), height = image.height());
+    Ok(image)
+}
+
+/// Vectorizes an already decoded image, skipping the encode/decode round trip.
+/// 16-bit and float images are quantized without rounding to 8 bits first.
+pub fn vectorize_image(image: &DynamicImage, options: &VectorizeOptions) -> String {
+    Pipeline::default().run_image(image, options)
+}
+
+pub fn vectorize_rgba_image(image: &RgbaImage, options: &VectorizeOptions) -> String {
+    Pipeline::default().run(image, options)
+}
 
-    let quantized = quantize_image(&rgba, options);
-    let svg = render_svg(&quantized, options);
+/// Vectorizes a tightly packed RGBA8 buffer (four bytes per pixel, row-major).
+pub fn vectorize_rgba(
+    width: u32,
+    height: u32,
+    data: &[u8],
+    options: &VectorizeOptions,
+) -> Result<String, VectorizeError> {
+    check_buffer_len(width, height, 4, data.len())?;
+    let image = RgbaImage::from_raw(width, height, data.to_vec())
+        .ok_or_else(|| VectorizeError::Vectorize("could not wrap RGBA buffer".into()))?;
+    Ok(vectorize_rgba_image(&image, options))
+}
 
-    Ok(svg)
+/// Vectorizes a tightly packed RGB8 buffer (three bytes per pixel, row-major).
+pub fn vectorize_rgb(
+    width: u32,
+    height: u32,
+    data: &[u8],
+    options: &VectorizeOptions,
+) -> Result<String, VectorizeError> {
+    check_buffer_len(width, height, 3, data.len())?;
+    let image = RgbImage::from_raw(width, height, data.to_vec())
+        .ok_or_else(|| VectorizeError::Vectorize("could not wrap RGB buffer".into()))?;
+    Ok(vectorize_image(&DynamicImage::ImageRgb8(image), options))
+}
+
+/// Vectorizes an 8-bit grayscale buffer (one byte per pixel, row-major).
+pub fn vectorize_gray(
+    width: u32,
+    height: u32,
+    data: &[u8],
+    options: &VectorizeOptions,
+) -> Result<String, VectorizeError> {
+    check_buffer_len(width, height, 1, data.len())?;
+    let image = GrayImage::from_raw(width, height, data.to_vec())
+        .ok_or_else(|| VectorizeError::Vectorize("could not wrap grayscale buffer".into()))?;
+    Ok(vectorize_image(&DynamicImage::ImageLuma8(image), options))
+}
+
+pub(crate) fn check_buffer_len(width: u32, height: u32, channels: usize, actual: usize) -> Result<(), VectorizeError> {
+    // 32-bit targets (wasm) overflow long before a buffer that size could exist.
+    let expected = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|pixels| pixels.checked_mul(channels));
+    if width == 0 || height == 0 || expected != Some(actual) {
+        return Err(VectorizeError::BufferSize {
+            width,
+            height,
+            expected: expected.unwrap_or(usize::MAX),
+            actual,
+        });
+    }
+    Ok(())
 }
 
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 pub fn png_to_svg_wasm(png_bytes: &[u8], options_json: &str) -> Result<String, JsValue> {
-    let options = if options_json.trim().is_empty() {
-        VectorizeOptions::default()
+    let options = options_from_json(options_json)?;
+    png_to_svg(png_bytes, &options).map_err(|err| JsValue::from_str(&err.to_string()))
+}
+
+/// Vectorizes canvas pixels directly, e.g. `vectorize_rgba_wasm(imageData.data, imageData.width, imageData.height, json)`.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn vectorize_rgba_wasm(
+    data: wasm_bindgen::Clamped<Vec<u8>>,
+    width: u32,
+    height: u32,
+    options_json: &str,
+) -> Result<String, JsValue> {
+    let options = options_from_json(options_json)?;
+    vectorize_rgba(width, height, &data.0, &options).map_err(|err| JsValue::from_str(&err.to_string()))
+}
+
+/// Vectorizes an `ImageData` object obtained from a 2D canvas context.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn vectorize_image_data_wasm(image_data: &web_sys::ImageData, options_json: &str) -> Result<String, JsValue> {
+    let options = options_from_json(options_json)?;
+    vectorize_rgba(image_data.width(), image_data.height(), &image_data.data().0, &options)
+        .map_err(|err| JsValue::from_str(&err.to_string()))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn options_from_json(options_json: &str) -> Result<VectorizeOptions, JsValue> {
+    if options_json.trim().is_empty() {
+        Ok(VectorizeOptions::default())
     } else {
         serde_json::from_str::<VectorizeOptions>(options_json)
-            .map_err(|err| JsValue::from_str(&format!("invalid options json: {err}")))?
-    };
-
-    png_to_svg(png_bytes, &options).map_err(|err| JsValue::from_str(&err.to_string()))
+            .map_err(|err| JsValue::from_str(&format!("invalid options json: {err}")))
+    }
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -82,25 +241,42 @@ pub fn default_options_json() -> String {
     serde_json::to_string(&VectorizeOptions::default()).unwrap_or_else(|_| "{}".to_string())
 }
 
-fn palette_size_from_options(options: &VectorizeOptions) -> usize {
+pub(crate) fn palette_size_from_options(options: &VectorizeOptions) -> usize {
     let clamped_detail = options.detail.clamp(0.1, 1.0);
     let base = options.colors.max(2) as f32;
     (base * clamped_detail).ceil() as usize
 }
 
+/// RGBA image with 16 bits per channel, the precision used for quantization.
+pub type Rgba16Image = ImageBuffer<Rgba<u16>, Vec<u16>>;
+
+/// Palette plus one palette index per pixel, in row-major order.
 #[derive(Debug, Clone)]
-struct QuantizedImage {
-    palette: Vec<[u8; 4]>,
-    indices: Vec<usize>,
-    width: u32,
-    height: u32,
+pub struct QuantizedImage {
+    pub palette: Vec<[u8; 4]>,
+    pub indices: Vec<usize>,
+    pub width: u32,
+    pub height: u32,
+    /// Color of a removed background (see [`BackgroundOptions::keep_rect`]) to fill the canvas
+    /// with underneath the traced regions.
+    pub background: Option<[u8; 4]>,
 }
 
-fn quantize_image(image: &RgbaImage, options: &VectorizeOptions) -> QuantizedImage {
+pub(crate) fn quantize_image(image: &RgbaImage, options: &VectorizeOptions) -> QuantizedImage {
+    quantize_image16(&widen(image), options)
+}
+
+/// Quantizes 16-bit-per-channel pixels. Palette building, averaging and pixel mapping all run
+/// at full precision; palette colors are rounded to 8 bits only in the result.
+///
+/// Alpha is a fourth quantization channel, so translucent areas get palette entries of their
+/// own. Pixels at or below [`VectorizeOptions::alpha_threshold`] share one transparent entry.
+pub(crate) fn quantize_image16(image: &Rgba16Image, options: &VectorizeOptions) -> QuantizedImage {
     let palette_size = palette_size_from_options(options);
+    let cutoff = u16::from(options.alpha_threshold) * 257;
     
     // Check if image has transparent pixels
-    let has_transparency = image.pixels().any(|p| p[3] < 255);
+    let has_transparency = image.pixels().any(|p| p[3] <= cutoff);
     
     // Reserve one slot for transparent if needed, otherwise use full palette_size
     let opaque_palette_size = if has_transparency {
@@ -109,28 +285,76 @@ fn quantize_image(image: &RgbaImage, options: &VectorizeOptions) -> QuantizedIma
         palette_size
     };
     
-    let mut palette = build_palette(image, opaque_palette_size.max(1));
+    let mut palette = {
+        stage_span!("palette");
+        let palette = build_palette(image, opaque_palette_size.max(1), cutoff);
+        stage_event!(colors = palette.len(), requested = opaque_palette_size.max(1));
+        palette
+    };
     
     // Add transparent color to palette if image has transparency
     if has_transparency {
         palette.push([0, 0, 0, 0]);
     }
     
-    let indices = map_to_palette(image, &palette);
+    let indices = {
+        stage_span!("mapping");
+        let indices = map_to_palette(image, &palette, cutoff);
+        stage_event!(pixels = indices.len(), palette = palette.len());
+        indices
+    };
+
+    // Entries only the low bits told apart share one 8-bit color, and so one layer.
+    let mut narrowed: Vec<[u8; 4]> = Vec::with_capacity(palette.len());
+    let remap: Vec<usize> = palette
+        .into_iter()
+        .map(|color| {
+            let color = narrow(color);
+            narrowed.iter().position(|&known| known == color).unwrap_or_else(|| {
+                narrowed.push(color);
+                narrowed.len() - 1
+            })
+        })
+        .collect();
 
     QuantizedImage {
-        palette,
-        indices,
+        palette: narrowed,
+        indices: indices.into_iter().map(|index| remap[index]).collect(),
         width: image.width(),
         height: image.height(),
+        background: None,
+    }
+}
+
+/// Exact 8- to 16-bit expansion (`0xab` becomes `0xabab`).
+pub(crate) fn widen(image: &RgbaImage) -> Rgba16Image {
+    let data = image.as_raw().iter().map(|&value| u16::from(value) * 257).collect();
+    Rgba16Image::from_raw(image.width(), image.height(), data).expect("buffer matches the image size")
+}
+
+/// The 16-bit source of images with more than 8 bits per channel, so quantization can use it.
+pub(crate) fn high_precision(image: &DynamicImage) -> Option<Rgba16Image> {
+    match image {
+        DynamicImage::ImageRgba16(rgba) => Some(rgba.clone()),
+        DynamicImage::ImageLuma16(_)
+        | DynamicImage::ImageLumaA16(_)
+        | DynamicImage::ImageRgb16(_)
+        | DynamicImage::ImageRgb32F(_)
+        | DynamicImage::ImageRgba32F(_) => Some(image.to_rgba16()),
+        _ => None,
     }
 }
 
-fn build_palette(image: &RgbaImage, max_colors: usize) -> Vec<[u8; 4]> {
+fn narrow([r, g, b, a]: [u16; 4]) -> [u8; 4] {
+    let round = |value: u16| ((u32::from(value) * 255 + 32767) / 65535) as u8;
+    [round(r), round(g), round(b), round(a)]
+}
+
+fn build_palette(image: &Rgba16Image, max_colors: usize, alpha_cutoff: u16) -> Vec<[u16; 4]> {
     // Collect all non-transparent pixels
-    let mut pixels: Vec<[u8; 4]> = Vec::new();
+    let mut pixels: Vec<[u16; 4]> = Vec::new();
     for pixel in image.pixels() {
-        if pixel[3] > 0 {
+        if pixel[3] > alpha_cutoff {
             pixels.push(pixel.0);
         }
     }
@@ -139,12 +363,11 @@ fn build_palette(image: &RgbaImage, max_colors: usize) -> Vec<[u8; 4]> {
         return vec![[0, 0, 0, 0]];
     }
 
-    if pixels.len() <= max_colors {
-        // If we have fewer unique pixels than max_colors, just return unique colors
-        let mut unique: Vec<[u8; 4]> = pixels.into_iter().collect::<std::collections::HashSet<_>>().into_iter().collect();
-        if unique.is_empty() {
-            unique.push([0, 0, 0, 0]);
-        }
+    // If we have fewer unique pixels than max_colors, just return unique colors (in the order
+    // they first appear, so output is stable), keeping every alpha level apart
+    let mut seen = HashSet::new();
+    let unique: Vec<[u16; 4]> = pixels.iter().copied().filter(|&pixel| seen.insert(pixel)).take(max_colors + 1).collect();
+    if unique.len() <= max_colors {
         return unique;
     }
 
@@ -154,17 +377,19 @@ fn build_palette(image: &RgbaImage, max_colors: usize) -> Vec<[u8; 4]> {
 
 #[derive(Clone)]
 struct ColorBox {
-    pixels: Vec<[u8; 4]>,
-    r_min: u8,
-    r_max: u8,
-    g_min: u8,
-    g_max: u8,
-    b_min: u8,
-    b_max: u8,
+    pixels: Vec<[u16; 4]>,
+    r_min: u16,
+    r_max: u16,
+    g_min: u16,
+    g_max: u16,
+    b_min: u16,
+    b_max: u16,
+    a_min: u16,
+    a_max: u16,
 }
 
 impl ColorBox {
-    fn new(pixels: Vec<[u8; 4]>) -> Self {
+    fn new(pixels: Vec<[u16; 4]>) -> Self {
         if pixels.is_empty() {
             return Self {
                 pixels,
@@ -174,23 +399,29 @@ impl ColorBox {
                 g_max: 0,
                 b_min: 0,
                 b_max: 0,
+                a_min: 0,
+                a_max: 0,
             };
         }
 
-        let mut r_min = 255u8;
-        let mut r_max = 0u8;
-        let mut g_min = 255u8;
-        let mut g_max = 0u8;
-        let mut b_min = 255u8;
-        let mut b_max = 0u8;
+        let mut r_min = u16::MAX;
+        let mut r_max = 0u16;
+        let mut g_min = u16::MAX;
+        let mut g_max = 0u16;
+        let mut b_min = u16::MAX;
+        let mut b_max = 0u16;
+        let mut a_min = u16::MAX;
+        let mut a_max = 0u16;
 
-        for &[r, g, b, _] in &pixels {
+        for &[r, g, b, a] in &pixels {
             r_min = r_min.min(r);
             r_max = r_max.max(r);
             g_min = g_min.min(g);
             g_max = g_max.max(g);
             b_min = b_min.min(b);
             b_max = b_max.max(b);
+            a_min = a_min.min(a);
+            a_max = a_max.max(a);
         }
 
         Self {
@@ -201,15 +432,20 @@ impl ColorBox {
             g_max,
             b_min,
             b_max,
+            a_min,
+            a_max,
         }
     }
 
     fn longest_dimension(&self) -> usize {
-        let r_range = (self.r_max as i32 - self.r_min as i32) as u32;
-        let g_range = (self.g_max as i32 - self.g_min as i32) as u32;
-        let b_range = (self.b_max as i32 - self.b_min as i32) as u32;
-
-        if r_range >= g_range && r_range >= b_range {
+        let r_range = self.r_max - self.r_min;
+        let g_range = self.g_max - self.g_min;
+        let b_range = self.b_max - self.b_min;
+        let a_range = self.a_max - self.a_min;
+
+        if a_range > r_range && a_range > g_range && a_range > b_range {
+            3 // A
+        } else if r_range >= g_range && r_range >= b_range {
             0 // R
         } else if g_range >= b_range {
             1 // G
@@ -218,34 +454,31 @@ impl ColorBox {
         }
     }
 
-    fn average_color(&self) -> [u8; 4] {
+    /// Mean color in linear light, so mixed boxes do not come out too dark. Colors are
+    /// weighted by alpha, as when compositing, so faint pixels barely tint the result; alpha
+    /// is averaged as is.
+    fn average_color(&self) -> [u16; 4] {
         if self.pixels.is_empty() {
             return [0, 0, 0, 0];
         }
 
-        let mut r_sum = 0u32;
-        let mut g_sum = 0u32;
-        let mut b_sum = 0u32;
-        let mut a_sum = 0u32;
-
+        let mut linear = [0f64; 3];
+        let mut alpha = 0u64;
         for &[r, g, b, a] in &self.pixels {
-            r_sum += r as u32;
-            g_sum += g as u32;
-            b_sum += b as u32;
-            a_sum += a as u32;
+            for (sum, channel) in linear.iter_mut().zip([r, g, b]) {
+                *sum += f64::from(srgb_to_linear16(channel)) * f64::from(a);
+            }
+            alpha += u64::from(a);
         }
 
-        let count = self.pixels.len() as u32;
-        [
-            (r_sum / count) as u8,
-            (g_sum / count) as u8,
-            (b_sum / count) as u8,
-            (a_sum / count) as u8,
-        ]
+        let count = self.pixels.len();
+        let weight = alpha.max(1) as f64;
+        let [r, g, b] = linear.map(|sum| linear_to_srgb16((sum / weight) as f32));
+        [r, g, b, ((alpha + count as u64 / 2) / count as u64) as u16]
     }
 }
 
-fn median_cut_quantize(pixels: &[[u8; 4]], max_colors: usize) -> Vec<[u8; 4]> {
+fn median_cut_quantize(pixels: &[[u16; 4]], max_colors: usize) -> Vec<[u16; 4]> {
     if pixels.is_empty() {
         return vec![[0, 0, 0, 0]];
     }
@@ -296,21 +529,21 @@ fn median_cut_quantize(pixels: &[[u8; 4]], max_colors: usize) -> Vec<[u8; 4]> {
     }
 
     // Return average colors from each box
-    let mut palette: Vec<[u8; 4]> = boxes.iter().map(|b| b.average_color()).collect();
+    let mut palette: Vec<[u16; 4]> = boxes.iter().map(|b| b.average_color()).collect();
     
     // If we have fewer colors than requested and there are still unique colors, try to add more
     if palette.len() < max_colors && !pixels.is_empty() {
         // Collect unique colors from pixels
-        let unique_colors: std::collections::HashSet<[u8; 4]> = pixels.iter().copied().collect();
+        let unique_colors: std::collections::HashSet<[u16; 4]> = pixels.iter().copied().collect();
         if unique_colors.len() > palette.len() {
             // Add unique colors that aren't already in palette
             for &color in &unique_colors {
                 if palette.len() >= max_colors {
                     break;
                 }
-                // Check if color is similar to any in palette
+                // Check if color is similar to any in palette (100 in 8-bit units)
                 let is_similar = palette.iter().any(|&pal_color| {
-                    color_distance(color, pal_color) < 100 // Threshold for "similar"
+                    color_distance16(color, pal_color) < 100 * 257 * 257
                 });
                 if !is_similar {
                     palette.push(color);
@@ -322,12 +555,12 @@ fn median_cut_quantize(pixels: &[[u8; 4]], max_colors: usize) -> Vec<[u8; 4]> {
     palette
 }
 
-fn map_to_palette(image: &RgbaImage, palette: &[[u8; 4]]) -> Vec<usize> {
+fn map_to_palette(image: &Rgba16Image, palette: &[[u16; 4]], alpha_cutoff: u16) -> Vec<usize> {
     // Find transparent color index (should be last if present)
     let transparent_idx = palette.iter().position(|&c| c[3] == 0);
     
     // Build separate palettes for opaque and transparent
-    let opaque_palette: Vec<(usize, [u8; 4])> = palette
+    let opaque_palette: Vec<(usize, [u16; 4])> = palette
         .iter()
         .enumerate()
         .filter(|(_, c)| c[3] > 0)
@@ -338,16 +571,16 @@ fn map_to_palette(image: &RgbaImage, palette: &[[u8; 4]]) -> Vec<usize> {
         .pixels()
         .map(|pixel| {
             // If pixel is transparent, map to transparent palette entry
-            if pixel[3] == 0 {
+            if pixel[3] <= alpha_cutoff {
                 transparent_idx.unwrap_or(0)
             } else if opaque_palette.is_empty() {
                 0
             } else {
                 // Find nearest opaque color
                 let mut best_idx = 0;
-                let mut best_dist = u32::MAX;
+                let mut best_dist = u64::MAX;
                 for &(orig_idx, color) in &opaque_palette {
-                    let dist = color_distance(pixel.0, color);
+                    let dist = color_distance16(pixel.0, color);
                     if dist < best_dist {
                         best_idx = orig_idx;
                         best_dist = dist;
@@ -359,7 +592,11 @@ fn map_to_palette(image: &RgbaImage, palette: &[[u8; 4]]) -> Vec<usize> {
         .collect()
 }
 
-fn color_distance(a: [u8; 4], b: [u8; 4]) -> u32 {
+fn color_distance16(a: [u16; 4], b: [u16; 4]) -> u64 {
+    a.iter().zip(&b).map(|(&a, &b)| (i64::from(a) - i64::from(b)).pow(2) as u64).sum()
+}
+
+pub(crate) fn color_distance(a: [u8; 4], b: [u8; 4]) -> u32 {
     let dr = a[0] as i32 - b[0] as i32;
     let dg = a[1] as i32 - b[1] as i32;
     let db = a[2] as i32 - b[2] as i32;
@@ -367,115 +604,179 @@ fn color_distance(a: [u8; 4], b: [u8; 4]) -> u32 {
     (dr * dr + dg * dg + db * db + da * da) as u32
 }
 
-fn render_svg(quantized: &QuantizedImage, options: &VectorizeOptions) -> String {
-    let mut svg = String::with_capacity(quantized.width as usize * quantized.height as usize / 10);
-    writeln!(
-        svg,
-        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {w} {h}\" aria-label=\"vectorized\">",
-        w = quantized.width,
-        h = quantized.height
-    )
-    .ok();
+/// A connected component of a single palette color.
+#[derive(Debug, Clone)]
+pub struct Region {
+    pub color_idx: usize,
+    pub pixels: HashSet<(i32, i32)>,
+    holes: OnceLock<Vec<HashSet<(i32, i32)>>>,
+}
+
+impl Region {
+    pub fn new(color_idx: usize, pixels: HashSet<(i32, i32)>) -> Self {
+        Self {
+            color_idx,
+            pixels,
+            holes: OnceLock::new(),
+        }
+    }
 
-    // Group paths by color
-    let mut paths_by_color: HashMap<usize, Vec<String>> = HashMap::new();
+    /// Pixels enclosed by the region but not part of it, split into 4-connected holes. Found
+    /// from `pixels` on the first call and cached, since tracing needs them for both the outer
+    /// outline and the hole outlines.
+    pub fn holes(&self) -> &[HashSet<(i32, i32)>] {
+        self.holes.get_or_init(|| find_holes(&self.pixels))
+    }
+}
 
-    // For each color, find connected components and trace contours
+/// Raw (unsimplified) outline for a region, in image coordinates.
+#[derive(Debug, Clone)]
+pub struct TracedRegion {
+    pub color_idx: usize,
+    pub outline: Vec<Point>,
+    /// Raw outlines of enclosed holes, which the region does not cover.
+    pub holes: Vec<Vec<Point>>,
+    /// Set when contour tracing failed and `outline` is the region's bounding box.
+    pub fallback: bool,
+}
+
+pub(crate) fn label_regions(quantized: &QuantizedImage) -> Vec<Region> {
+    stage_span!("labeling");
+    let mut regions = Vec::new();
     for (color_idx, &color) in quantized.palette.iter().enumerate() {
         if color[3] == 0 {
             continue; // Skip transparent
         }
 
-        // Find connected components for this color
-        let components = find_connected_components(quantized, color_idx);
-        
-        for component in components {
-            // Try to trace contour for this component
-            // If tracing fails, create a bounding polygon to ensure all components are rendered
-            if let Some(contour) = trace_contour(quantized, &component, color_idx) {
-                // For logo mode, skip simplification entirely for 1-1 match
-                let simplified = match options.mode {
-                    VectorizeMode::Logo => contour, // No simplification - preserve every point
-                    VectorizeMode::Poster => {
-                        let tolerance = options.tolerance * 0.5;
-                        rdp_simplify(&contour, tolerance.max(0.3))
-                    },
-                    VectorizeMode::PixelArt => {
-                        let tolerance = options.tolerance * 2.0;
-                        rdp_simplify(&contour, tolerance)
-                    },
-                };
-                
-                // Generate SVG path
-                let path_d = points_to_path(&simplified, options);
-                if !path_d.is_empty() {
-                    paths_by_color.entry(color_idx).or_insert_with(Vec::new).push(path_d);
-                }
-            } else {
-                // Tracing failed - create a simple bounding polygon as fallback
-                // This ensures all components are rendered, even if contour tracing fails
-                let min_x = component.iter().map(|p| p.0).min().unwrap_or(0);
-                let max_x = component.iter().map(|p| p.0).max().unwrap_or(0);
-                let min_y = component.iter().map(|p| p.1).min().unwrap_or(0);
-                let max_y = component.iter().map(|p| p.1).max().unwrap_or(0);
-                
-                if max_x > min_x && max_y > min_y {
-                    let path_d = format!("M {} {} L {} {} L {} {} L {} {} Z",
-                        min_x, min_y,
-                        max_x + 1, min_y,
-                        max_x + 1, max_y + 1,
-                        min_x, max_y + 1
-                    );
-                    paths_by_color.entry(color_idx).or_insert_with(Vec::new).push(path_d);
-                } else if component.len() == 1 {
-                    // Single pixel fallback
-                    let (px, py) = component.iter().next().unwrap();
-                    let path_d = format!("M {} {} L {} {} L {} {} L {} {} Z",
-                        px, py,
-                        px + 1, py,
-                        px + 1, py + 1,
-                        px, py + 1
-                    );
-                    paths_by_color.entry(color_idx).or_insert_with(Vec::new).push(path_d);
-                }
-            }
+        for pixels in find_connected_components(quantized, color_idx) {
+            regions.push(Region::new(color_idx, pixels));
+        }
+    }
+    stage_event!(components = regions.len());
+    regions
+}
+
+pub(crate) fn trace_regions(quantized: &QuantizedImage, regions: &[Region], tracer: &dyn Tracer) -> Vec<TracedRegion> {
+    stage_span!("tracing");
+    let mut traced = Vec::with_capacity(regions.len());
+    for region in regions {
+        let component = &region.pixels;
+        // Try to trace contour for this component
+        // If tracing fails, create a bounding polygon to ensure all components are rendered
+        if let Some(outline) = tracer.trace(quantized, region) {
+            traced.push(TracedRegion {
+                color_idx: region.color_idx,
+                outline,
+                holes: tracer.trace_holes(quantized, region),
+                fallback: false,
+            });
+            continue;
+        }
+
+        let min_x = component.iter().map(|p| p.0).min().unwrap_or(0);
+        let max_x = component.iter().map(|p| p.0).max().unwrap_or(0);
+        let min_y = component.iter().map(|p| p.1).min().unwrap_or(0);
+        let max_y = component.iter().map(|p| p.1).max().unwrap_or(0);
+
+        // Degenerate strips (one pixel wide but longer than one pixel) have no usable box.
+        if (max_x > min_x && max_y > min_y) || component.len() == 1 {
+            let (x0, y0, x1, y1) = (min_x as f32, min_y as f32, (max_x + 1) as f32, (max_y + 1) as f32);
+            traced.push(TracedRegion {
+                color_idx: region.color_idx,
+                outline: vec![
+                    Point::new(x0, y0),
+                    Point::new(x1, y0),
+                    Point::new(x1, y1),
+                    Point::new(x0, y1),
+                ],
+                holes: Vec::new(),
+                fallback: true,
+            });
         }
     }
+    stage_event!(
+        contours = traced.len(),
+        holes = traced.iter().map(|region| region.holes.len()).sum::<usize>(),
+        fallbacks = traced.iter().filter(|region| region.fallback).count(),
+        points = traced.iter().map(|region| region.outline.len()).sum::<usize>()
+    );
+    traced
+}
+
+pub(crate) fn build_document(
+    quantized: &QuantizedImage,
+    traced: &[TracedRegion],
+    simplifier: &dyn Simplifier,
+    options: &VectorizeOptions,
+) -> VectorDocument {
+    stage_span!("simplification");
+    let mut document = VectorDocument::new(quantized.width, quantized.height);
+    if let Some(color) = quantized.background {
+        let (width, height) = (quantized.width as f32, quantized.height as f32);
+        let layer = document.layer_mut(quantized.palette.len(), color);
+        layer.background = true;
+        layer.paths.push(VectorPath::rect(0.0, 0.0, width, height));
+    }
+
+    for region in traced {
+        let mut path = if region.fallback {
+            // Tracing failed - emit the bounding polygon as-is
+            VectorPath::polygon(&region.outline)
+        } else {
+            let simplified = simplifier.simplify(&region.outline, options);
+            fit_path(&simplified, options)
+        };
+
+        if path.is_empty() {
+            continue;
+        }
 
-    // Output paths grouped by color
-    for (color_idx, paths) in paths_by_color {
-        let color = quantized.palette[color_idx];
-        let opacity = opacity_from_options(color[3], options);
-        let hex = to_hex(color);
-        
-        writeln!(
-            svg,
-            "  <g fill=\"#{hex}\" fill-opacity=\"{opacity:.3}\">",
-            hex = hex,
-            opacity = opacity
-        )
-        .ok();
-        
-        for path_d in paths {
-            writeln!(svg, "    <path d=\"{}\"/>", path_d).ok();
+        // Holes become extra subpaths, cut out by the even-odd fill rule.
+        for hole in &region.holes {
+            let simplified = simplifier.simplify(hole, options);
+            path.commands.extend(fit_path(&simplified, options).commands);
         }
-        
-        writeln!(svg, "  </g>").ok();
+
+        document
+            .layer_mut(region.color_idx, quantized.palette[region.color_idx])
+            .paths
+            .push(path);
     }
 
-    svg.push_str("</svg>");
-    svg
+    stage_event!(
+        points_before = traced
+            .iter()
+            .map(|region| region.outline.len() + region.holes.iter().map(Vec::len).sum::<usize>())
+            .sum::<usize>(),
+        points_after = document.node_count(),
+        paths = document.path_count()
+    );
+    document
+}
+
+pub(crate) fn simplify_outline(outline: &[Point], options: &VectorizeOptions) -> Vec<Point> {
+    match options.mode {
+        VectorizeMode::Logo | VectorizeMode::Auto => outline.to_vec(), // No simplification - preserve every point
+        VectorizeMode::Poster => {
+            let tolerance = options.tolerance * 0.5;
+            rdp_simplify(outline, tolerance.max(0.3))
+        }
+        VectorizeMode::PixelArt => {
+            let tolerance = options.tolerance * 2.0;
+            rdp_simplify(outline, tolerance)
+        }
+    }
 }
 
-// Point type for contours with sub-pixel precision
+/// Contour point with sub-pixel precision, in image coordinates.
 #[derive(Debug, Clone, Copy, PartialEq)]
-struct Point {
-    x: f32,
-    y: f32,
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
 }
 
 impl Point {
-    fn new(x: f32, y: f32) -> Self {
+    pub fn new(x: f32, y: f32) -> Self {
         Self { x, y }
     }
 }
@@ -547,11 +848,85 @@ fn find_connected_components(quantized: &QuantizedImage, color_idx: usize) -> Ve
     components
 }
 
-// Trace contour using simple, reliable boundary following
-fn trace_contour(
+// Trace contour using simple, reliable boundary following. Pixels in `holes` count as part of
+// the component, so only the outer boundary is followed.
+pub(crate) fn trace_contour(
     quantized: &QuantizedImage,
     component: &HashSet<(i32, i32)>,
-    color_idx: usize,
+    holes: &[HashSet<(i32, i32)>],
+) -> Option<Vec<Point>> {
+    follow_boundary(quantized, component, |pixel| {
+        component.contains(&pixel) || holes.iter().any(|hole| hole.contains(&pixel))
+    })
+}
+
+/// Outline of a hole found by [`find_holes`], through the centers of its edge pixels so it
+/// lines up with the outer contours of whatever fills the hole.
+pub(crate) fn trace_hole(quantized: &QuantizedImage, hole: &HashSet<(i32, i32)>) -> Option<Vec<Point>> {
+    follow_boundary(quantized, hole, |pixel| hole.contains(&pixel))
+}
+
+/// Pixels enclosed by `component` but not part of it, split into 4-connected holes.
+pub(crate) fn find_holes(component: &HashSet<(i32, i32)>) -> Vec<HashSet<(i32, i32)>> {
+    let (Some(min_x), Some(max_x)) = (component.iter().map(|p| p.0).min(), component.iter().map(|p| p.0).max()) else {
+        return Vec::new();
+    };
+    let min_y = component.iter().map(|p| p.1).min().unwrap_or(0);
+    let max_y = component.iter().map(|p| p.1).max().unwrap_or(0);
+
+    // Grid over the bounding box plus a one-pixel ring, which is always outside the component.
+    let (origin_x, origin_y) = (min_x - 1, min_y - 1);
+    let grid_width = (max_x - min_x + 3) as usize;
+    let grid_height = (max_y - min_y + 3) as usize;
+    const UNSEEN: u8 = 0;
+    const FILLED: u8 = 1;
+    const OUTSIDE: u8 = 2;
+    let mut grid = vec![UNSEEN; grid_width * grid_height];
+    for &(x, y) in component {
+        grid[(y - origin_y) as usize * grid_width + (x - origin_x) as usize] = FILLED;
+    }
+
+    let flood = |grid: &mut Vec<u8>, start: usize, mark: u8| -> Vec<usize> {
+        let mut filled = vec![start];
+        let mut stack = vec![start];
+        grid[start] = mark;
+        while let Some(cell) = stack.pop() {
+            let (x, y) = (cell % grid_width, cell / grid_width);
+            let neighbors = [
+                (x > 0).then(|| cell - 1),
+                (x + 1 < grid_width).then(|| cell + 1),
+                (y > 0).then(|| cell - grid_width),
+                (y + 1 < grid_height).then(|| cell + grid_width),
+            ];
+            for next in neighbors.into_iter().flatten() {
+                if grid[next] == UNSEEN {
+                    grid[next] = mark;
+                    filled.push(next);
+                    stack.push(next);
+                }
+            }
+        }
+        filled
+    };
+
+    flood(&mut grid, 0, OUTSIDE);
+    let mut holes = Vec::new();
+    for cell in 0..grid.len() {
+        if grid[cell] == UNSEEN {
+            let hole = flood(&mut grid, cell, OUTSIDE)
+                .into_iter()
+                .map(|cell| ((cell % grid_width) as i32 + origin_x, (cell / grid_width) as i32 + origin_y))
+                .collect();
+            holes.push(hole);
+        }
+    }
+    holes
+}
+
+fn follow_boundary(
+    quantized: &QuantizedImage,
+    component: &HashSet<(i32, i32)>,
+    inside: impl Fn((i32, i32)) -> bool,
 ) -> Option<Vec<Point>> {
     if component.is_empty() {
         return None;
@@ -576,8 +951,7 @@ fn trace_contour(
                     is_boundary = true;
                     break;
                 }
-                let nidx = (ny as usize) * width + (nx as usize);
-                if quantized.indices[nidx] != color_idx {
+                if !inside((nx, ny)) {
                     is_boundary = true;
                     break;
                 }
@@ -773,89 +1147,85 @@ fn rdp_simplify(points: &[Point], tolerance: f32) -> Vec<Point> {
 }
 
 fn point_to_line_dist_sq(p: Point, line_p1: Point, line_p2: Point) -> f32 {
-    let dx = (line_p2.x - line_p1.x) as f32;
-    let dy = (line_p2.y - line_p1.y) as f32;
+    let dx = line_p2.x - line_p1.x;
+    let dy = line_p2.y - line_p1.y;
     let len_sq = dx * dx + dy * dy;
 
     if len_sq < 1e-6 {
         // Line segment is a point
-        let px = (p.x - line_p1.x) as f32;
-        let py = (p.y - line_p1.y) as f32;
+        let px = p.x - line_p1.x;
+        let py = p.y - line_p1.y;
         return px * px + py * py;
     }
 
-    let t = ((p.x - line_p1.x) as f32 * dx + (p.y - line_p1.y) as f32 * dy) / len_sq;
+    let t = ((p.x - line_p1.x) * dx + (p.y - line_p1.y) * dy) / len_sq;
     let t = t.clamp(0.0, 1.0);
 
-    let proj_x = line_p1.x as f32 + t * dx;
-    let proj_y = line_p1.y as f32 + t * dy;
+    let proj_x = line_p1.x + t * dx;
+    let proj_y = line_p1.y + t * dy;
 
-    let px = p.x as f32 - proj_x;
-    let py = p.y as f32 - proj_y;
+    let px = p.x - proj_x;
+    let py = p.y - proj_y;
 
     px * px + py * py
 }
 
-// Convert points to SVG path - simple and reliable
-fn points_to_path(points: &[Point], options: &VectorizeOptions) -> String {
+// Convert points to path commands - simple and reliable
+fn fit_path(points: &[Point], options: &VectorizeOptions) -> VectorPath {
+    let mut path = VectorPath::default();
     if points.len() < 2 {
-        return String::new();
+        return path;
     }
 
-    let mut path = String::new();
     let smoothness = options.smoothness.clamp(0.0, 1.0);
 
     // Start path
-    write!(path, "M {:.2} {:.2}", points[0].x, points[0].y).ok();
-    
+    path.commands.push(PathCommand::MoveTo(points[0]));
+
     // For logo mode with high smoothness, use curves; otherwise use lines
-    if matches!(options.mode, VectorizeMode::Logo) && smoothness > 0.5 && points.len() > 4 {
+    if matches!(options.mode, VectorizeMode::Logo | VectorizeMode::Auto) && smoothness > 0.5 && points.len() > 4 {
         // Use smooth cubic Bézier curves for logos
         for i in 1..points.len() {
             let p0 = points[i - 1];
             let p1 = points[i];
-            
+
             if i == points.len() - 1 {
                 // Last point - line to close
-                write!(path, " L {:.2} {:.2}", p1.x, p1.y).ok();
+                path.commands.push(PathCommand::LineTo(p1));
             } else {
                 let p2 = points[i + 1];
-                
+
                 // Calculate control points for smooth curve
                 let dx1 = p1.x - p0.x;
                 let dy1 = p1.y - p0.y;
                 let dx2 = p2.x - p1.x;
                 let dy2 = p2.y - p1.y;
-                
+
                 // Control points extend from p1 towards p0 and p2
-                let cp1x = p1.x - dx1 * smoothness * 0.3;
-                let cp1y = p1.y - dy1 * smoothness * 0.3;
-                let cp2x = p1.x + dx2 * smoothness * 0.3;
-                let cp2y = p1.y + dy2 * smoothness * 0.3;
-                
-                write!(path, " C {:.2} {:.2} {:.2} {:.2} {:.2} {:.2}", 
-                    cp1x, cp1y, cp2x, cp2y, p1.x, p1.y).ok();
+                let ctrl1 = Point::new(p1.x - dx1 * smoothness * 0.3, p1.y - dy1 * smoothness * 0.3);
+                let ctrl2 = Point::new(p1.x + dx2 * smoothness * 0.3, p1.y + dy2 * smoothness * 0.3);
+
+                path.commands.push(PathCommand::CubicTo { ctrl1, ctrl2, to: p1 });
             }
         }
     } else {
         // Simple polyline for accuracy
-        for p in points.iter().skip(1) {
-            write!(path, " L {:.2} {:.2}", p.x, p.y).ok();
+        for &p in points.iter().skip(1) {
+            path.commands.push(PathCommand::LineTo(p));
         }
     }
-    
-    path.push_str(" Z");
+
+    path.commands.push(PathCommand::Close);
     path
 }
 
-fn opacity_from_options(alpha: u8, _options: &VectorizeOptions) -> f32 {
-    // For vectorization, we want full opacity based on the alpha channel
-    // Don't use smoothness to affect opacity - that was causing paths to be invisible
-    let base = alpha as f32 / 255.0;
-    base.max(0.95) // Ensure paths are visible (at least 95% opacity for non-transparent pixels)
+/// Fill opacity of a palette color: its alpha, unchanged. Transparent pixels never reach the
+/// emitters, since their palette entry is not traced.
+pub(crate) fn opacity_from_options(alpha: u8, _options: &VectorizeOptions) -> f32 {
+    alpha as f32 / 255.0
 }
 
-fn to_hex(color: [u8; 4]) -> String {
+pub(crate) fn to_hex(color: [u8; 4]) -> String {
     let mut s = String::with_capacity(6);
     write!(&mut s, "{:02x}{:02x}{:02x}", color[0], color[1], color[2]).ok();
     s
@@ -864,7 +1234,7 @@ fn to_hex(color: [u8; 4]) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use image::{codecs::png::PngEncoder, ColorType, DynamicImage, ImageEncoder};
+    use image::{codecs::png::PngEncoder, ColorType, ImageEncoder, Rgba};
     use serde_json::json;
 
     #[test]
@@ -896,17 +1266,82 @@ mod tests {
         );
     }
 
+    #[test]
+    fn raw_buffers_vectorize_without_encoding() {
+        let image = RgbaImage::from_fn(4, 4, |x, _| {
+            if x < 2 {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0, 0, 255, 255])
+            }
+        });
+        let options = VectorizeOptions::default();
+
+        let svg = vectorize_rgba(4, 4, image.as_raw(), &options).expect("rgba buffer should vectorize");
+        assert!(svg.contains("ff0000"));
+        assert!(svg.contains("0000ff"));
+
+        let rgb: Vec<u8> = image.pixels().flat_map(|p| [p[0], p[1], p[2]]).collect();
+        let from_rgb = vectorize_rgb(4, 4, &rgb, &options).expect("rgb buffer should vectorize");
+        assert!(from_rgb.contains("ff0000"));
+
+        let gray = [0u8, 0, 255, 255].repeat(4);
+        let from_gray = vectorize_gray(4, 4, &gray, &options).expect("gray buffer should vectorize");
+        assert!(from_gray.contains("ffffff"));
+    }
+
+    #[test]
+    fn rings_are_traced_with_holes() {
+        // A 3-pixel-thick red ring around a 6x6 blue square.
+        let image = RgbaImage::from_fn(12, 12, |x, y| {
+            if (3..9).contains(&x) && (3..9).contains(&y) {
+                Rgba([0, 0, 255, 255])
+            } else {
+                Rgba([255, 0, 0, 255])
+            }
+        });
+        let document = Pipeline::default().document(&image, &VectorizeOptions::default());
+        let ring = document
+            .layers
+            .iter()
+            .find(|layer| layer.color == [255, 0, 0, 255])
+            .expect("ring layer");
+        assert_eq!(ring.paths.len(), 1);
+        assert!(ring.paths[0].has_holes());
+
+        let svg = SvgEmitter.emit(&document, &VectorizeOptions::default());
+        assert!(svg.contains("fill-rule=\"evenodd\""));
+    }
+
+    #[test]
+    fn rejects_mismatched_buffer_length() {
+        let options = VectorizeOptions::default();
+        let err = vectorize_rgba(4, 4, &[0u8; 15], &options).unwrap_err();
+        assert!(matches!(
+            err,
+            VectorizeError::BufferSize {
+                expected: 64,
+                actual: 15,
+                ..
+            }
+        ));
+        assert!(matches!(
+            check_buffer_len(u32::MAX, u32::MAX, 4, 16),
+            Err(VectorizeError::BufferSize { expected: usize::MAX, .. })
+        ));
+    }
+
     #[test]
     fn respects_palette_size() {
         let image = DynamicImage::new_rgba8(4, 4).to_rgba8();
-        let palette = build_palette(&image, 4);
+        let palette = build_palette(&widen(&image), 4, 0);
         assert_eq!(palette.len(), 1, "empty images fall back to one color");
 
         let non_empty = RgbaImage::from_fn(4, 4, |x, y| {
             let alpha = if (x + y) % 2 == 0 { 255 } else { 128 };
             Rgba([x as u8 * 10, y as u8 * 10, 50, alpha])
         });
-        let palette = build_palette(&non_empty, 3);
+        let palette = build_palette(&widen(&non_empty), 3, 0);
         assert!(palette.len() <= 3);
     }
 
@@ -932,6 +1367,90 @@ mod tests {
         assert!(serialized.contains("\"mode\":\"pixel\""));
     }
 
+    #[test]
+    fn translucent_pixels_keep_their_alpha() {
+        // An opaque square with a drop shadow fading from alpha 128 to 64.
+        let image = RgbaImage::from_fn(8, 8, |x, y| match (x, y) {
+            (0..=4, 0..=4) => Rgba([200, 30, 30, 255]),
+            (5, _) | (_, 5) => Rgba([0, 0, 0, 128]),
+            (6, _) | (_, 6) => Rgba([0, 0, 0, 64]),
+            _ => Rgba([0, 0, 0, 0]),
+        });
+        let svg = vectorize_rgba_image(&image, &VectorizeOptions::default());
+        assert!(svg.contains(r##"fill="#c81e1e" fill-opacity="1.000""##));
+        assert!(svg.contains(r##"fill="#000000" fill-opacity="0.502""##));
+        assert!(svg.contains(r##"fill="#000000" fill-opacity="0.251""##));
+
+        let options = VectorizeOptions {
+            alpha_threshold: 100,
+            ..VectorizeOptions::default()
+        };
+        let svg = vectorize_rgba_image(&image, &options);
+        assert!(svg.contains(r##"fill-opacity="0.502""##));
+        assert!(!svg.contains(r##"fill-opacity="0.251""##));
+    }
+
+    #[test]
+    fn background_is_removed_or_kept_as_one_rect() {
+        let image = RgbaImage::from_fn(10, 10, |x, y| match (x, y) {
+            (3..=6, 3..=6) => Rgba([20, 20, 160, 255]),
+            _ => Rgba([250, 252, 255, 255]),
+        });
+        let mut options = VectorizeOptions {
+            background: Some(BackgroundOptions::default()),
+            ..VectorizeOptions::default()
+        };
+        let svg = vectorize_rgba_image(&image, &options);
+        assert_eq!(svg.matches("<path").count(), 1);
+        assert!(!svg.contains("<rect"));
+
+        options.background = Some(BackgroundOptions {
+            keep_rect: true,
+            ..BackgroundOptions::default()
+        });
+        let svg = vectorize_rgba_image(&image, &options);
+        assert_eq!(svg.matches("<path").count(), 1);
+        assert!(svg.contains("<g fill=\"#fafcff\" fill-opacity=\"1.000\">\n    <rect width=\"10\" height=\"10\"/>"));
+    }
+
+    #[test]
+    fn only_the_kept_background_is_written_as_a_rect() {
+        let mut document = VectorDocument::new(10, 10);
+        document.layer_mut(0, [20, 20, 160, 255]).paths.push(VectorPath::rect(0.0, 0.0, 10.0, 10.0));
+        let svg = SvgEmitter.emit(&document, &VectorizeOptions::default());
+        assert_eq!(svg.matches("<path").count(), 1);
+        assert!(!svg.contains("<rect"));
+    }
+
+    #[test]
+    fn colors_are_averaged_in_linear_light() {
+        let black_and_white = ColorBox::new(vec![[0, 0, 0, u16::MAX], [u16::MAX; 4]]);
+        assert_eq!(narrow(black_and_white.average_color()), [188, 188, 188, 255]);
+    }
+
+    #[test]
+    fn high_bit_depth_reaches_the_quantizer() {
+        struct Probe(std::rc::Rc<std::cell::Cell<u16>>);
+        impl Quantizer for Probe {
+            fn quantize(&self, image: &RgbaImage, options: &VectorizeOptions) -> QuantizedImage {
+                quantize_image(image, options)
+            }
+            fn quantize_rgba16(&self, image: &Rgba16Image, options: &VectorizeOptions) -> QuantizedImage {
+                self.0.set(image.get_pixel(0, 0)[0]);
+                quantize_image16(image, options)
+            }
+        }
+
+        // Four reds that are all 0x12 at 8 bits.
+        let image = Rgba16Image::from_fn(4, 1, |x, _| Rgba([0x1200 + x as u16 * 0x30, 0, 0, u16::MAX]));
+        let seen = std::rc::Rc::default();
+        let pipeline = Pipeline::builder().quantizer(Probe(std::rc::Rc::clone(&seen))).build();
+        let quantized = pipeline.quantize_image(&DynamicImage::ImageRgba16(image), &VectorizeOptions::default());
+        assert_eq!(seen.get(), 0x1200);
+        assert_eq!(quantized.palette, [[0x12, 0, 0, 255]]);
+        assert_eq!(quantized.indices, [0, 0, 0, 0]);
+    }
+
     #[test]
     fn quantize_image_tracks_dimensions() {
         let image = RgbaImage::from_fn(3, 2, |x, y| {
@@ -0,0 +1,385 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use image::{DynamicImage, ImageFormat};
+use serde_json::{json, Value};
+
+use crate::indexed::read_png_indices;
+use crate::{label_regions, Point, QuantizedImage, VectorizeError};
+
+/// A segmentation mask: one class ID per pixel, in row-major order.
+///
+/// Indexed PNGs keep their palette indices and grayscale images their raw values; any other
+/// image packs each pixel's RGB into a 24-bit ID.
+#[derive(Debug, Clone)]
+pub struct LabelMask {
+    pub width: u32,
+    pub height: u32,
+    pub labels: Vec<u32>,
+    /// Display color for each class, taken from the PNG palette when there is one.
+    pub colors: BTreeMap<u32, [u8; 4]>,
+}
+
+impl LabelMask {
+    pub fn decode(bytes: &[u8]) -> Result<Self, VectorizeError> {
+        stage_span!("decode");
+        if image::guess_format(bytes)? == ImageFormat::Png {
+            if let Some(mask) = decode_png_indices(bytes)? {
+                return Ok(mask);
+            }
+        }
+        Ok(Self::from_image(&image::load_from_memory(bytes)?))
+    }
+
+    pub fn from_image(image: &DynamicImage) -> Self {
+        let (width, height) = (image.width(), image.height());
+        let labels = match image {
+            DynamicImage::ImageLuma8(gray) => gray.pixels().map(|p| u32::from(p[0])).collect(),
+            DynamicImage::ImageLuma16(gray) => gray.pixels().map(|p| u32::from(p[0])).collect(),
+            DynamicImage::ImageLumaA8(gray) => gray.pixels().map(|p| u32::from(p[0])).collect(),
+            DynamicImage::ImageLumaA16(gray) => gray.pixels().map(|p| u32::from(p[0])).collect(),
+            _ => image
+                .to_rgb8()
+                .pixels()
+                .map(|p| u32::from(p[0]) << 16 | u32::from(p[1]) << 8 | u32::from(p[2]))
+                .collect(),
+        };
+        let packed_rgb = !matches!(
+            image,
+            DynamicImage::ImageLuma8(_)
+                | DynamicImage::ImageLuma16(_)
+                | DynamicImage::ImageLumaA8(_)
+                | DynamicImage::ImageLumaA16(_)
+        );
+        let mut mask = LabelMask {
+            width,
+            height,
+            labels,
+            colors: BTreeMap::new(),
+        };
+        mask.fill_missing_colors(|label| {
+            if packed_rgb {
+                [(label >> 16) as u8, (label >> 8) as u8, label as u8, 255]
+            } else {
+                let level = label.min(255) as u8;
+                [level, level, level, 255]
+            }
+        });
+        mask
+    }
+
+    fn fill_missing_colors(&mut self, color: impl Fn(u32) -> [u8; 4]) {
+        for &label in &self.labels {
+            self.colors.entry(label).or_insert_with(|| color(label));
+        }
+    }
+
+    /// The mask as a quantized image with one palette entry per class, in ascending ID order.
+    fn quantized(&self) -> (QuantizedImage, Vec<u32>) {
+        let classes: Vec<u32> = self.colors.keys().copied().collect();
+        let slots: BTreeMap<u32, usize> = classes.iter().enumerate().map(|(slot, &class)| (class, slot)).collect();
+        let quantized = QuantizedImage {
+            palette: classes.iter().map(|class| self.colors[class]).collect(),
+            indices: self.labels.iter().map(|label| slots[label]).collect(),
+            width: self.width,
+            height: self.height,
+            background: None,
+        };
+        (quantized, classes)
+    }
+}
+
+/// Mask from the raw values of an indexed or grayscale PNG; `None` for color PNGs.
+fn decode_png_indices(bytes: &[u8]) -> Result<Option<LabelMask>, VectorizeError> {
+    let Some(raw) = read_png_indices(bytes)? else {
+        return Ok(None);
+    };
+    let mut mask = LabelMask {
+        width: raw.width,
+        height: raw.height,
+        labels: raw.values,
+        colors: BTreeMap::new(),
+    };
+    let palette = raw.palette;
+    let bits = u32::from(raw.bit_depth);
+    mask.fill_missing_colors(|label| match &palette {
+        Some(palette) => palette.get(label as usize).map_or([0, 0, 0, 255], |&[r, g, b, _]| [r, g, b, 255]),
+        // Gray levels of low bit depths are stretched so masks stay visible in debug output.
+        None => {
+            let max = (1u32 << bits) - 1;
+            let level = (label * 255 / max.max(1)).min(255) as u8;
+            [level, level, level, 255]
+        }
+    });
+    Ok(Some(mask))
+}
+
+/// Settings for [`mask_to_coco`].
+#[derive(Debug, Clone)]
+pub struct CocoOptions {
+    /// `file_name` recorded for the single image entry.
+    pub file_name: String,
+    /// Class ID that is not annotated, usually `0`.
+    pub background: Option<u32>,
+}
+
+impl Default for CocoOptions {
+    fn default() -> Self {
+        Self {
+            file_name: "mask.png".to_string(),
+            background: Some(0),
+        }
+    }
+}
+
+/// COCO-style JSON with one annotation per connected instance of every class.
+///
+/// Instances are outlined along their pixel edges, without quantization or smoothing, so each
+/// polygon encloses exactly the instance's pixels: `area` is its pixel count and `bbox` its
+/// extent. COCO polygons cannot have holes, so each hole is joined to the outline through a
+/// zero-width cut.
+pub fn mask_to_coco(mask: &LabelMask, options: &CocoOptions) -> String {
+    let (quantized, classes) = mask.quantized();
+    let regions = label_regions(&quantized);
+
+    stage_span!("tracing");
+    let mut annotations = Vec::new();
+    for region in &regions {
+        let category = classes[region.color_idx];
+        if Some(category) == options.background {
+            continue;
+        }
+
+        let xs = || region.pixels.iter().map(|p| p.0);
+        let ys = || region.pixels.iter().map(|p| p.1);
+        let (min_x, max_x) = (xs().min().unwrap_or(0), xs().max().unwrap_or(0));
+        let (min_y, max_y) = (ys().min().unwrap_or(0), ys().max().unwrap_or(0));
+
+        let mut rings = pixel_edge_rings(&region.pixels).into_iter();
+        let outline = rings.next().unwrap_or_default();
+        let ring = drop_collinear(bridge_holes(outline, rings));
+        let segmentation: Vec<f32> = ring.iter().flat_map(|p| [p.x, p.y]).collect();
+
+        annotations.push(json!({
+            "id": annotations.len() + 1,
+            "image_id": 1,
+            "category_id": category,
+            "segmentation": [segmentation],
+            "area": region.pixels.len(),
+            "bbox": [min_x, min_y, max_x - min_x + 1, max_y - min_y + 1],
+            "iscrowd": 0,
+        }));
+    }
+    stage_event!(annotations = annotations.len());
+
+    let categories: Vec<Value> = classes
+        .iter()
+        .filter(|&&class| Some(class) != options.background)
+        .map(|class| json!({ "id": class, "name": format!("class_{class}") }))
+        .collect();
+    json!({
+        "images": [{
+            "id": 1,
+            "file_name": options.file_name,
+            "width": mask.width,
+            "height": mask.height,
+        }],
+        "annotations": annotations,
+        "categories": categories,
+    })
+    .to_string()
+}
+
+/// Rings along the pixel edges around `pixels`, open (the last vertex does not repeat the
+/// first): the outline first, clockwise on screen, then one counter-clockwise ring per hole.
+///
+/// Where two pixels touch only at a corner the ring passes from one to the other, keeping
+/// 8-connected instances in one outline.
+fn pixel_edge_rings(pixels: &HashSet<(i32, i32)>) -> Vec<Vec<Point>> {
+    // Unit edges with the instance on their right.
+    let mut edges: HashMap<(i32, i32), Vec<(i32, i32)>> = HashMap::new();
+    for &(x, y) in pixels {
+        let sides = [
+            ((x, y - 1), (x, y), (x + 1, y)),
+            ((x + 1, y), (x + 1, y), (x + 1, y + 1)),
+            ((x, y + 1), (x + 1, y + 1), (x, y + 1)),
+            ((x - 1, y), (x, y + 1), (x, y)),
+        ];
+        for (neighbor, from, to) in sides {
+            if !pixels.contains(&neighbor) {
+                edges.entry(from).or_default().push(to);
+            }
+        }
+    }
+    // At a corner with two ways on, turn left, into the diagonal neighbor.
+    let next = |from: (i32, i32), at: (i32, i32)| {
+        let outgoing = &edges[&at];
+        let left = (at.0 + (at.1 - from.1), at.1 - (at.0 - from.0));
+        if outgoing.contains(&left) {
+            left
+        } else {
+            outgoing[0]
+        }
+    };
+
+    // The top edge of the top-left pixel sorts first and is on the outline.
+    let mut starts: Vec<((i32, i32), (i32, i32))> =
+        edges.iter().flat_map(|(&from, ends)| ends.iter().map(move |&to| (from, to))).collect();
+    starts.sort_by_key(|&((x0, y0), (x1, y1))| (y0, x0, y1, x1));
+    let mut used = HashSet::new();
+    let mut rings = Vec::new();
+    for edge in starts {
+        if used.contains(&edge) {
+            continue;
+        }
+        let mut ring = Vec::new();
+        let (mut from, mut to) = edge;
+        while used.insert((from, to)) {
+            ring.push(Point::new(from.0 as f32, from.1 as f32));
+            (from, to) = (to, next(from, to));
+        }
+        rings.push(ring);
+    }
+    rings
+}
+
+/// Splices each hole into `outer` at the closest pair of vertices, walking in and back out
+/// along the same cut.
+fn bridge_holes(mut outer: Vec<Point>, holes: impl Iterator<Item = Vec<Point>>) -> Vec<Point> {
+    let distance = |a: Point, b: Point| (a.x - b.x).powi(2) + (a.y - b.y).powi(2);
+    for mut hole in holes {
+        let mut best = (0, 0, f32::MAX);
+        for (i, &a) in outer.iter().enumerate() {
+            for (j, &b) in hole.iter().enumerate() {
+                if distance(a, b) < best.2 {
+                    best = (i, j, distance(a, b));
+                }
+            }
+        }
+        let (i, j, _) = best;
+        hole.rotate_left(j);
+        let entry = outer[i];
+        let mut spliced = outer[..=i].to_vec();
+        spliced.extend(&hole);
+        spliced.push(hole[0]);
+        spliced.push(entry);
+        spliced.extend(&outer[i + 1..]);
+        outer = spliced;
+    }
+    outer
+}
+
+/// Removes vertices in the middle of straight runs, treating `ring` as closed.
+fn drop_collinear(ring: Vec<Point>) -> Vec<Point> {
+    let n = ring.len();
+    if n < 4 {
+        return ring;
+    }
+    (0..n)
+        .filter(|&i| {
+            let (prev, p, next) = (ring[(i + n - 1) % n], ring[i], ring[(i + 1) % n]);
+            let cross = (p.x - prev.x) * (next.y - p.y) - (p.y - prev.y) * (next.x - p.x);
+            let forward = (p.x - prev.x) * (next.x - p.x) + (p.y - prev.y) * (next.y - p.y);
+            // Keep turns, and the reversals at either end of a hole cut.
+            cross != 0.0 || forward <= 0.0
+        })
+        .map(|i| ring[i])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayAlphaImage, GrayImage, Luma, LumaA};
+
+    #[test]
+    fn annotates_instances_with_holes() {
+        // Class 3 is a ring around a class 0 hole; class 7 is a separate block.
+        let image = GrayImage::from_fn(12, 8, |x, y| match (x, y) {
+            (3..=4, 3..=4) => Luma([0]),
+            (1..=6, 1..=6) => Luma([3]),
+            (8..=10, 2..=4) => Luma([7]),
+            _ => Luma([0]),
+        });
+        let mask = LabelMask::from_image(&DynamicImage::ImageLuma8(image));
+        let coco: Value = serde_json::from_str(&mask_to_coco(&mask, &CocoOptions::default())).unwrap();
+
+        assert_eq!(coco["images"][0]["width"], 12);
+        assert_eq!(coco["categories"], json!([{ "id": 3, "name": "class_3" }, { "id": 7, "name": "class_7" }]));
+        let annotations = coco["annotations"].as_array().unwrap();
+        assert_eq!(annotations.len(), 2);
+
+        let ring = annotations.iter().find(|a| a["category_id"] == 3).unwrap();
+        assert_eq!(ring["area"], 32);
+        assert_eq!(ring["bbox"], json!([1, 1, 6, 6]));
+        // The hole is cut out along its pixel edges.
+        assert!(polygon(ring).contains(&(3.0, 3.0)));
+        assert_eq!(polygon_area(ring), 32.0);
+
+        let block = annotations.iter().find(|a| a["category_id"] == 7).unwrap();
+        assert_eq!(block["area"], 9);
+        assert_eq!(polygon(block), [(8.0, 2.0), (11.0, 2.0), (11.0, 5.0), (8.0, 5.0)]);
+    }
+
+    #[test]
+    fn thin_and_diagonal_instances_enclose_their_pixels() {
+        // A one-pixel line (class 1) and a diagonal staircase (class 2) touching only at corners.
+        let image = GrayImage::from_fn(8, 5, |x, y| match (x, y) {
+            (0..=4, 0) => Luma([1]),
+            (2..=4, 2..=4) if x - 2 == y - 2 => Luma([2]),
+            _ => Luma([0]),
+        });
+        let mask = LabelMask::from_image(&DynamicImage::ImageLuma8(image));
+        let coco: Value = serde_json::from_str(&mask_to_coco(&mask, &CocoOptions::default())).unwrap();
+        let annotations = coco["annotations"].as_array().unwrap();
+        assert_eq!(annotations.len(), 2);
+
+        let line = annotations.iter().find(|a| a["category_id"] == 1).unwrap();
+        assert_eq!(polygon(line), [(0.0, 0.0), (5.0, 0.0), (5.0, 1.0), (0.0, 1.0)]);
+        let diagonal = annotations.iter().find(|a| a["category_id"] == 2).unwrap();
+        assert_eq!(diagonal["area"], 3);
+        assert_eq!(diagonal["bbox"], json!([2, 2, 3, 3]));
+        assert_eq!(polygon_area(diagonal), 3.0);
+    }
+
+    fn polygon(annotation: &Value) -> Vec<(f64, f64)> {
+        let coordinates = annotation["segmentation"][0].as_array().unwrap();
+        coordinates.chunks(2).map(|p| (p[0].as_f64().unwrap(), p[1].as_f64().unwrap())).collect()
+    }
+
+    /// Shoelace area; hole cuts have zero width and add nothing.
+    fn polygon_area(annotation: &Value) -> f64 {
+        let points = polygon(annotation);
+        let twice: f64 = points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .map(|(a, b)| a.0 * b.1 - b.0 * a.1)
+            .sum();
+        twice.abs() / 2.0
+    }
+
+    #[test]
+    fn gray_alpha_masks_keep_their_gray_levels() {
+        let image = GrayAlphaImage::from_fn(4, 2, |x, _| LumaA([if x < 2 { 3 } else { 0 }, 255]));
+        let mask = LabelMask::from_image(&DynamicImage::ImageLumaA8(image));
+        assert_eq!(mask.labels, [3, 3, 0, 0, 3, 3, 0, 0]);
+        assert_eq!(mask.colors[&3], [3, 3, 3, 255]);
+    }
+
+    #[test]
+    fn indexed_png_keeps_palette_indices() {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, 4, 2);
+            encoder.set_color(png::ColorType::Indexed);
+            encoder.set_depth(png::BitDepth::Four);
+            encoder.set_palette(vec![0, 0, 0, 255, 0, 0, 0, 255, 0, 0, 0, 255, 9, 9, 9, 1, 2, 3]);
+            let mut writer = encoder.write_header().unwrap();
+            // Indices 0 0 5 5 / 0 2 2 5, two per byte.
+            writer.write_image_data(&[0x00, 0x55, 0x02, 0x25]).unwrap();
+        }
+        let mask = LabelMask::decode(&bytes).unwrap();
+        assert_eq!(mask.labels, [0, 0, 5, 5, 0, 2, 2, 5]);
+        assert_eq!(mask.colors[&2], [0, 255, 0, 255]);
+        assert_eq!(mask.colors[&5], [1, 2, 3, 255]);
+    }
+}
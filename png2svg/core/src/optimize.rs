@@ -0,0 +1,258 @@
+use std::str::FromStr;
+
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+
+use crate::{Emitter, VectorizeMode, VectorizeOptions, Vectorizer};
+
+/// Palette sizes tried by [`optimize`]; `detail` only scales the palette size, so it stays at 1.0.
+const PALETTE_SIZES: [u8; 14] = [2, 3, 4, 5, 6, 8, 10, 12, 16, 20, 24, 32, 48, 64];
+/// Simplification tolerances tried for the modes that simplify.
+const TOLERANCES: [f32; 8] = [0.3, 0.5, 0.75, 1.0, 1.5, 2.0, 3.0, 5.0];
+
+/// What [`optimize`] makes as small as possible once every limit is met.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Objective {
+    /// Reconstruction error, for the most faithful output within a size budget.
+    #[default]
+    Error,
+    /// Path nodes, for the simplest output within an error budget.
+    Nodes,
+    /// Emitted bytes, for the smallest file within an error budget.
+    Bytes,
+}
+
+impl FromStr for Objective {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "error" => Ok(Objective::Error),
+            "nodes" => Ok(Objective::Nodes),
+            "bytes" | "size" => Ok(Objective::Bytes),
+            _ => Err(format!("objective must be one of: error, nodes, bytes (got {value:?})")),
+        }
+    }
+}
+
+/// Limits the optimized output must stay within, and what to minimize inside them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OptimizeTarget {
+    pub max_nodes: Option<usize>,
+    pub max_paths: Option<usize>,
+    pub max_bytes: Option<usize>,
+    /// Largest acceptable [`reconstruction_error`].
+    pub max_error: Option<f32>,
+    pub minimize: Objective,
+}
+
+/// Size and fidelity of one vectorization.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct OutputMetrics {
+    /// Path vertices, as counted by [`VectorDocument::node_count`](crate::VectorDocument::node_count).
+    pub nodes: usize,
+    pub paths: usize,
+    /// Length of the emitter's output.
+    pub bytes: usize,
+    pub error: f32,
+}
+
+/// The options [`optimize`] settled on.
+#[derive(Debug, Clone, Serialize)]
+pub struct Optimized {
+    pub options: VectorizeOptions,
+    pub metrics: OutputMetrics,
+    /// Whether every limit is met; if not, `options` are the ones that came closest.
+    pub met: bool,
+    /// Number of option sets tried.
+    pub evaluated: usize,
+}
+
+/// Root-mean-square difference (0-255) between two images as they look on white, so
+/// transparent areas compare by what a viewer sees rather than by their hidden color.
+pub fn reconstruction_error(source: &RgbaImage, rendered: &RgbaImage) -> f32 {
+    let on_white = |[r, g, b, a]: [u8; 4]| {
+        let alpha = f32::from(a) / 255.0;
+        [r, g, b].map(|channel| f32::from(channel) * alpha + 255.0 * (1.0 - alpha))
+    };
+    let mut sum = 0f64;
+    for (p, q) in source.pixels().zip(rendered.pixels()) {
+        let (p, q) = (on_white(p.0), on_white(q.0));
+        sum += p.iter().zip(&q).map(|(a, b)| f64::from((a - b).powi(2))).sum::<f64>();
+    }
+    let samples = (source.width() as usize * source.height() as usize * 3).max(1);
+    (sum / samples as f64).sqrt() as f32
+}
+
+/// Searches palette size, mode and simplification tolerance for the options that best meet
+/// `target`, measuring each candidate's output and its [`reconstruction_error`] against the
+/// source image. Sizes are those of the vectorizer's own emitter (SVG by default); see
+/// [`optimize_with`] for other formats. The vectorizer is left set to the chosen options.
+///
+/// Candidates are the vectorizer's current options with `colors`, `detail`, `mode` and
+/// `tolerance` varied: Logo mode (no simplification) and Poster mode at several tolerances,
+/// or only pixel-art tolerances if the current mode is `PixelArt`. Quantization is cached per
+/// palette size, so each size is quantized and traced once.
+pub fn optimize(vectorizer: &mut Vectorizer, target: &OptimizeTarget) -> Optimized {
+    search(vectorizer, target, None)
+}
+
+/// Like [`optimize`], with output sizes measured in `emitter`'s format.
+pub fn optimize_with(vectorizer: &mut Vectorizer, target: &OptimizeTarget, emitter: &dyn Emitter) -> Optimized {
+    search(vectorizer, target, Some(emitter))
+}
+
+fn search(vectorizer: &mut Vectorizer, target: &OptimizeTarget, emitter: Option<&dyn Emitter>) -> Optimized {
+    stage_span!("optimize");
+    let base = vectorizer.options().clone();
+    let path_settings: Vec<(VectorizeMode, f32)> = match base.mode {
+        VectorizeMode::PixelArt => TOLERANCES.iter().map(|&tolerance| (VectorizeMode::PixelArt, tolerance)).collect(),
+        _ => std::iter::once((VectorizeMode::Logo, base.tolerance))
+            .chain(TOLERANCES.iter().map(|&tolerance| (VectorizeMode::Poster, tolerance)))
+            .collect(),
+    };
+
+    let mut best: Option<(Optimized, (f32, f32, usize))> = None;
+    let mut evaluated = 0;
+    for colors in PALETTE_SIZES {
+        let mut exhausted = false;
+        for &(mode, tolerance) in &path_settings {
+            let options = VectorizeOptions {
+                colors,
+                detail: 1.0,
+                mode,
+                tolerance,
+                ..base.clone()
+            };
+            vectorizer.set_options(options.clone());
+            let metrics = measure(vectorizer, emitter);
+            evaluated += 1;
+            // Images with fewer colors than asked for give the same result for every larger size.
+            let quantized = vectorizer.quantized();
+            exhausted = quantized.palette.iter().filter(|color| color[3] > 0).count() < usize::from(colors);
+
+            let rank = rank(&metrics, target);
+            if best.as_ref().is_none_or(|(_, best_rank)| rank < *best_rank) {
+                let met = rank.0 == 0.0;
+                best = Some((
+                    Optimized {
+                        options,
+                        metrics,
+                        met,
+                        evaluated: 0,
+                    },
+                    rank,
+                ));
+            }
+        }
+        if exhausted {
+            break;
+        }
+    }
+
+    let (mut optimized, _) = best.expect("at least one candidate is evaluated");
+    optimized.evaluated = evaluated;
+    vectorizer.set_options(optimized.options.clone());
+    stage_event!(evaluated, nodes = optimized.metrics.nodes, error = optimized.metrics.error);
+    optimized
+}
+
+fn measure(vectorizer: &mut Vectorizer, emitter: Option<&dyn Emitter>) -> OutputMetrics {
+    let bytes = match emitter {
+        Some(emitter) => vectorizer.render_with(emitter).len(),
+        None => vectorizer.render().len(),
+    };
+    let document = vectorizer.document();
+    let (nodes, paths) = (document.node_count(), document.path_count());
+    let rendered = document.rasterize();
+    OutputMetrics {
+        nodes,
+        paths,
+        bytes,
+        error: reconstruction_error(vectorizer.image(), &rendered),
+    }
+}
+
+/// Sort key, lowest best: how far the limits are exceeded, then the objective, then nodes.
+fn rank(metrics: &OutputMetrics, target: &OptimizeTarget) -> (f32, f32, usize) {
+    let over = |value: f32, limit: Option<f32>| limit.map_or(0.0, |limit| (value / limit.max(f32::EPSILON) - 1.0).max(0.0));
+    let excess = over(metrics.nodes as f32, target.max_nodes.map(|n| n as f32))
+        + over(metrics.paths as f32, target.max_paths.map(|n| n as f32))
+        + over(metrics.bytes as f32, target.max_bytes.map(|n| n as f32))
+        + over(metrics.error, target.max_error);
+    let objective = match target.minimize {
+        Objective::Error => metrics.error,
+        Objective::Nodes => metrics.nodes as f32,
+        Objective::Bytes => metrics.bytes as f32,
+    };
+    (excess, objective, metrics.nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn rings() -> RgbaImage {
+        RgbaImage::from_fn(40, 40, |x, y| {
+            let d = ((x as f32 - 19.5).powi(2) + (y as f32 - 19.5).powi(2)).sqrt();
+            match d as u32 {
+                0..=5 => Rgba([220, 40, 40, 255]),
+                6..=11 => Rgba([40, 40, 200, 255]),
+                12..=17 => Rgba([240, 200, 40, 255]),
+                _ => Rgba([255, 255, 255, 255]),
+            }
+        })
+    }
+
+    #[test]
+    fn rasterized_document_matches_the_source() {
+        let mut vectorizer = Vectorizer::new(rings(), VectorizeOptions::default());
+        let rendered = vectorizer.document().rasterize();
+        assert_eq!(rendered.dimensions(), (40, 40));
+        assert_eq!(rendered.get_pixel(20, 20).0, [220, 40, 40, 255]);
+        let error = reconstruction_error(&rings(), &rendered);
+        assert!(error > 0.0 && error < 60.0, "error {error}");
+        assert_eq!(reconstruction_error(&rings(), &rings()), 0.0);
+    }
+
+    #[test]
+    fn meets_a_node_budget_with_the_least_error() {
+        let mut vectorizer = Vectorizer::new(rings(), VectorizeOptions::default());
+        let unlimited = optimize(&mut vectorizer, &OptimizeTarget::default());
+        assert!(unlimited.met);
+
+        let target = OptimizeTarget {
+            max_nodes: Some(unlimited.metrics.nodes / 3),
+            ..OptimizeTarget::default()
+        };
+        let budgeted = optimize(&mut vectorizer, &target);
+        assert!(budgeted.met);
+        assert!(budgeted.metrics.nodes <= unlimited.metrics.nodes / 3);
+        assert!(budgeted.metrics.error >= unlimited.metrics.error);
+        assert_eq!(vectorizer.options().colors, budgeted.options.colors);
+        assert_eq!(vectorizer.options().tolerance, budgeted.options.tolerance);
+
+        // The reverse: fewest nodes that keep the error in check.
+        let target = OptimizeTarget {
+            max_error: Some(budgeted.metrics.error),
+            minimize: Objective::Nodes,
+            ..OptimizeTarget::default()
+        };
+        let lean = optimize(&mut vectorizer, &target);
+        assert!(lean.met);
+        assert!(lean.metrics.nodes <= budgeted.metrics.nodes);
+    }
+
+    #[test]
+    fn sizes_are_measured_in_the_given_format() {
+        let mut vectorizer = Vectorizer::new(rings(), VectorizeOptions::default());
+        let svg = optimize(&mut vectorizer, &OptimizeTarget::default());
+        let emitter = crate::DxfEmitter::default();
+        let dxf = optimize_with(&mut vectorizer, &OptimizeTarget::default(), &emitter);
+        assert_eq!(dxf.metrics.bytes, vectorizer.render_with(&emitter).len());
+        assert!(dxf.metrics.bytes > svg.metrics.bytes);
+    }
+}
@@ -0,0 +1,459 @@
+use image::{DynamicImage, RgbaImage};
+
+use crate::background::remove_background;
+use crate::classify::resolve_auto;
+use crate::emit::SvgEmitter;
+use crate::{
+    build_document, decode_animation, decode_image, decode_indexed, fit_embedded_palette, high_precision, label_regions,
+    quantize_image, quantize_image16, simplify_outline, trace_contour, trace_hole, trace_regions, AnimationFrame, Frame,
+    Point, QuantizedImage, Region, Rgba16Image, TracedRegion, VectorDocument, VectorizeError, VectorizeMode,
+    VectorizeOptions, widen,
+};
+
+/// Reduces an image to a palette and a per-pixel palette index.
+pub trait Quantizer {
+    fn quantize(&self, image: &RgbaImage, options: &VectorizeOptions) -> QuantizedImage;
+
+    /// Quantizes a 16-bit-per-channel image. The default rounds it to 8 bits and calls
+    /// [`quantize`](Self::quantize).
+    fn quantize_rgba16(&self, image: &Rgba16Image, options: &VectorizeOptions) -> QuantizedImage {
+        self.quantize(&DynamicImage::ImageRgba16(image.clone()).to_rgba8(), options)
+    }
+
+    /// Whether a paletted image's own palette (from [`decode_indexed`]) may stand in for this
+    /// quantizer when it fits the requested palette size. The default is `false`, so a custom
+    /// quantizer always runs.
+    fn keeps_embedded_palette(&self) -> bool {
+        false
+    }
+}
+
+/// Follows the boundary of one region, returning `None` if no closed outline could be found.
+///
+/// Regions that fail to trace are still emitted, as their bounding box.
+pub trait Tracer {
+    fn trace(&self, quantized: &QuantizedImage, region: &Region) -> Option<Vec<Point>>;
+
+    /// Outlines of the holes inside a region, filled with the even-odd rule together with its
+    /// outer outline. The default reports none.
+    fn trace_holes(&self, _quantized: &QuantizedImage, _region: &Region) -> Vec<Vec<Point>> {
+        Vec::new()
+    }
+}
+
+/// Reduces the point count of a raw outline before it is turned into path commands.
+pub trait Simplifier {
+    fn simplify(&self, outline: &[Point], options: &VectorizeOptions) -> Vec<Point>;
+}
+
+/// Serializes a finished [`VectorDocument`] into an output format.
+pub trait Emitter {
+    fn emit(&self, document: &VectorDocument, options: &VectorizeOptions) -> String;
+
+    /// Serializes an animation. The default, for formats that cannot animate, emits the first
+    /// frame.
+    fn emit_frames(&self, frames: &[AnimationFrame], options: &VectorizeOptions) -> String {
+        match frames.first() {
+            Some(frame) => self.emit(&frame.document, options),
+            None => self.emit(&VectorDocument::new(0, 0), options),
+        }
+    }
+}
+
+impl<E: Emitter + ?Sized> Emitter for Box<E> {
+    fn emit(&self, document: &VectorDocument, options: &VectorizeOptions) -> String {
+        (**self).emit(document, options)
+    }
+
+    fn emit_frames(&self, frames: &[AnimationFrame], options: &VectorizeOptions) -> String {
+        (**self).emit_frames(frames, options)
+    }
+}
+
+/// Median-cut palette with a reserved transparent slot; the default quantizer. Works at 16 bits
+/// per channel either way.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MedianCutQuantizer;
+
+impl Quantizer for MedianCutQuantizer {
+    fn quantize(&self, image: &RgbaImage, options: &VectorizeOptions) -> QuantizedImage {
+        quantize_image(image, options)
+    }
+
+    fn quantize_rgba16(&self, image: &Rgba16Image, options: &VectorizeOptions) -> QuantizedImage {
+        quantize_image16(image, options)
+    }
+
+    fn keeps_embedded_palette(&self) -> bool {
+        true
+    }
+}
+
+/// Boundary-pixel following tracer; the default tracer. Enclosed holes are traced separately.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BoundaryTracer;
+
+impl Tracer for BoundaryTracer {
+    fn trace(&self, quantized: &QuantizedImage, region: &Region) -> Option<Vec<Point>> {
+        trace_contour(quantized, &region.pixels, region.holes())
+    }
+
+    fn trace_holes(&self, quantized: &QuantizedImage, region: &Region) -> Vec<Vec<Point>> {
+        region
+            .holes()
+            .iter()
+            .filter_map(|hole| trace_hole(quantized, hole))
+            .collect()
+    }
+}
+
+/// Ramer-Douglas-Peucker simplification scaled per [`VectorizeMode`](crate::VectorizeMode); the
+/// default simplifier. Logo mode keeps every traced point.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RdpSimplifier;
+
+impl Simplifier for RdpSimplifier {
+    fn simplify(&self, outline: &[Point], options: &VectorizeOptions) -> Vec<Point> {
+        simplify_outline(outline, options)
+    }
+}
+
+/// A composed set of pipeline stages.
+///
+/// ```
+/// use png2svg_core::{Pipeline, SvgEmitter, VectorizeOptions};
+///
+/// let pipeline = Pipeline::builder().emitter(SvgEmitter).build();
+/// let image = image::RgbaImage::from_pixel(4, 4, image::Rgba([200, 20, 20, 255]));
+/// let svg = pipeline.run(&image, &VectorizeOptions::default());
+/// assert!(svg.contains("c81414"));
+/// ```
+pub struct Pipeline {
+    quantizer: Box<dyn Quantizer>,
+    tracer: Box<dyn Tracer>,
+    simplifier: Box<dyn Simplifier>,
+    emitter: Box<dyn Emitter>,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl Pipeline {
+    pub fn builder() -> PipelineBuilder {
+        PipelineBuilder::default()
+    }
+
+    /// Quantizes `image`, first removing the background if the options ask for it.
+    pub fn quantize(&self, image: &RgbaImage, options: &VectorizeOptions) -> QuantizedImage {
+        match options.background {
+            Some(_) => self.quantize_rgba16(&widen(image), options),
+            None => self.quantizer.quantize(image, options),
+        }
+    }
+
+    pub fn quantize_rgba16(&self, image: &Rgba16Image, options: &VectorizeOptions) -> QuantizedImage {
+        let Some(background) = &options.background else {
+            return self.quantizer.quantize_rgba16(image, options);
+        };
+        let mut image = image.clone();
+        let color = remove_background(&mut image, background, u16::from(options.alpha_threshold) * 257);
+        let mut quantized = self.quantizer.quantize_rgba16(&image, options);
+        quantized.background = color.filter(|_| background.keep_rect);
+        quantized
+    }
+
+    /// Quantizes a decoded image at its own precision: 16-bit and float sources are not
+    /// rounded to 8 bits first.
+    pub fn quantize_image(&self, image: &DynamicImage, options: &VectorizeOptions) -> QuantizedImage {
+        match (image, high_precision(image)) {
+            (_, Some(precise)) => self.quantize_rgba16(&precise, options),
+            (DynamicImage::ImageRgba8(rgba), None) => self.quantize(rgba, options),
+            (other, None) => self.quantize(&other.to_rgba8(), options),
+        }
+    }
+
+    /// Splits the quantized image into connected regions of one color each.
+    pub fn label(&self, quantized: &QuantizedImage) -> Vec<Region> {
+        label_regions(quantized)
+    }
+
+    pub fn trace(&self, quantized: &QuantizedImage, regions: &[Region]) -> Vec<TracedRegion> {
+        trace_regions(quantized, regions, self.tracer.as_ref())
+    }
+
+    /// Simplifies traced outlines and fits them into path commands.
+    pub fn build_document(
+        &self,
+        quantized: &QuantizedImage,
+        traced: &[TracedRegion],
+        options: &VectorizeOptions,
+    ) -> VectorDocument {
+        build_document(quantized, traced, self.simplifier.as_ref(), options)
+    }
+
+    pub fn emit(&self, document: &VectorDocument, options: &VectorizeOptions) -> String {
+        emit_document(self.emitter.as_ref(), document, options)
+    }
+
+    /// The embedded palette, if the quantizer accepts one and it fits the options.
+    pub(crate) fn fit_embedded_palette(
+        &self,
+        embedded: &QuantizedImage,
+        options: &VectorizeOptions,
+    ) -> Option<QuantizedImage> {
+        if !self.quantizer.keeps_embedded_palette() {
+            return None;
+        }
+        fit_embedded_palette(embedded, options)
+    }
+
+    /// Runs every stage up to, but not including, emission.
+    pub fn document(&self, image: &RgbaImage, options: &VectorizeOptions) -> VectorDocument {
+        self.document_from_quantized(&self.quantize(image, options), options)
+    }
+
+    /// Runs the stages after quantization, e.g. on an embedded palette from [`decode_indexed`].
+    pub fn document_from_quantized(&self, quantized: &QuantizedImage, options: &VectorizeOptions) -> VectorDocument {
+        let regions = self.label(quantized);
+        let traced = self.trace(quantized, &regions);
+        self.build_document(quantized, &traced, options)
+    }
+
+    /// Builds a document for every frame of an animation, keeping each frame's delay.
+    ///
+    /// All frames are quantized together, so a color keeps the same palette entry (and an
+    /// unchanged region traces to the same path) throughout the animation. Fails if the frames,
+    /// stacked for quantization, would be taller than `u32::MAX` pixels.
+    pub fn animation(
+        &self,
+        frames: &[Frame],
+        options: &VectorizeOptions,
+    ) -> Result<Vec<AnimationFrame>, VectorizeError> {
+        let Some(first) = frames.first() else {
+            return Ok(Vec::new());
+        };
+        let options = &resolve_auto(&first.image, options);
+        let (width, height) = first.image.dimensions();
+        let stacked_height = u32::try_from(frames.len())
+            .ok()
+            .and_then(|count| height.checked_mul(count))
+            .ok_or_else(|| {
+                let count = frames.len();
+                VectorizeError::Vectorize(format!("{count} frames of {width}x{height} are too many to quantize together"))
+            })?;
+        let mut stacked = RgbaImage::new(width, stacked_height);
+        for (i, frame) in frames.iter().enumerate() {
+            image::imageops::replace(&mut stacked, &frame.image, 0, i64::from(height) * i as i64);
+        }
+        let shared = self.quantize(&stacked, options);
+
+        let frame_len = width as usize * height as usize;
+        Ok(frames
+            .iter()
+            .zip(shared.indices.chunks_exact(frame_len.max(1)))
+            .map(|(frame, indices)| {
+                let quantized = QuantizedImage {
+                    palette: shared.palette.clone(),
+                    indices: indices.to_vec(),
+                    width,
+                    height,
+                    background: shared.background,
+                };
+                let regions = self.label(&quantized);
+                let traced = self.trace(&quantized, &regions);
+                AnimationFrame {
+                    document: self.build_document(&quantized, &traced, options),
+                    delay_ms: frame.delay_ms,
+                }
+            })
+            .collect())
+    }
+
+    /// Runs every stage, resolving [`VectorizeMode::Auto`] for `image` first.
+    pub fn run(&self, image: &RgbaImage, options: &VectorizeOptions) -> String {
+        let options = &resolve_auto(image, options);
+        self.emit(&self.document(image, options), options)
+    }
+
+    /// Like [`run`](Self::run), keeping the precision of 16-bit and float images.
+    pub fn run_image(&self, image: &DynamicImage, options: &VectorizeOptions) -> String {
+        if options.mode == VectorizeMode::Auto {
+            return self.run_image(image, &resolve_auto(&image.to_rgba8(), options));
+        }
+        let document = self.document_from_quantized(&self.quantize_image(image, options), options);
+        self.emit(&document, options)
+    }
+
+    /// Decodes `bytes` (any format supported by `image`) and runs every stage. Paletted PNG/GIF
+    /// input that fits the requested palette size keeps its own palette instead of being
+    /// quantized (unless the quantizer opts out, see [`Quantizer::keeps_embedded_palette`]), and
+    /// animated GIF/APNG input goes to the emitter as an animation (classified by its first frame
+    /// in [`VectorizeMode::Auto`]).
+    pub fn run_bytes(&self, bytes: &[u8], options: &VectorizeOptions) -> Result<String, VectorizeError> {
+        // GIF/APNG frames come decoded; other images are decoded at most once, below.
+        let mut image = match decode_animation(bytes)? {
+            Some(frames) if frames.len() > 1 => {
+                let options = &resolve_auto(&frames[0].image, options);
+                let animation = self.animation(&frames, options)?;
+                stage_span!("emission");
+                return Ok(self.emitter.emit_frames(&animation, options));
+            }
+            Some(frames) => match frames.into_iter().next() {
+                Some(frame) => Some(DynamicImage::ImageRgba8(frame.image)),
+                None => return Err(VectorizeError::Vectorize("animation has no frames".into())),
+            },
+            None => None,
+        };
+        let options = match options.mode {
+            VectorizeMode::Auto => {
+                // Classification needs the pixels, even if the embedded palette is used after all.
+                let decoded = match image.take() {
+                    Some(decoded) => decoded,
+                    None => decode_image(bytes)?,
+                };
+                let resolved = resolve_auto(&decoded.to_rgba8(), options);
+                image = Some(decoded);
+                resolved
+            }
+            _ => options.clone(),
+        };
+        let embedded = if self.quantizer.keeps_embedded_palette() { decode_indexed(bytes)? } else { None };
+        if let Some(quantized) = embedded.and_then(|embedded| self.fit_embedded_palette(&embedded, &options)) {
+            return Ok(self.emit(&self.document_from_quantized(&quantized, &options), &options));
+        }
+        let image = match image {
+            Some(image) => image,
+            None => decode_image(bytes)?,
+        };
+        Ok(self.run_image(&image, &options))
+    }
+}
+
+pub(crate) fn emit_document(emitter: &dyn Emitter, document: &VectorDocument, options: &VectorizeOptions) -> String {
+    stage_span!("emission");
+    let output = emitter.emit(document, options);
+    stage_event!(bytes = output.len(), paths = document.path_count());
+    output
+}
+
+/// Builds a [`Pipeline`]; any stage left unset uses the default implementation.
+#[derive(Default)]
+pub struct PipelineBuilder {
+    quantizer: Option<Box<dyn Quantizer>>,
+    tracer: Option<Box<dyn Tracer>>,
+    simplifier: Option<Box<dyn Simplifier>>,
+    emitter: Option<Box<dyn Emitter>>,
+}
+
+impl PipelineBuilder {
+    pub fn quantizer(mut self, quantizer: impl Quantizer + 'static) -> Self {
+        self.quantizer = Some(Box::new(quantizer));
+        self
+    }
+
+    pub fn tracer(mut self, tracer: impl Tracer + 'static) -> Self {
+        self.tracer = Some(Box::new(tracer));
+        self
+    }
+
+    pub fn simplifier(mut self, simplifier: impl Simplifier + 'static) -> Self {
+        self.simplifier = Some(Box::new(simplifier));
+        self
+    }
+
+    pub fn emitter(mut self, emitter: impl Emitter + 'static) -> Self {
+        self.emitter = Some(Box::new(emitter));
+        self
+    }
+
+    pub fn build(self) -> Pipeline {
+        Pipeline {
+            quantizer: self.quantizer.unwrap_or_else(|| Box::new(MedianCutQuantizer)),
+            tracer: self.tracer.unwrap_or_else(|| Box::new(BoundaryTracer)),
+            simplifier: self.simplifier.unwrap_or_else(|| Box::new(RdpSimplifier)),
+            emitter: self.emitter.unwrap_or_else(|| Box::new(SvgEmitter)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    struct TwoTone;
+
+    impl Quantizer for TwoTone {
+        fn quantize(&self, image: &RgbaImage, _options: &VectorizeOptions) -> QuantizedImage {
+            QuantizedImage {
+                palette: vec![[0, 0, 0, 255], [255, 255, 255, 255]],
+                indices: image.pixels().map(|p| usize::from(p[0] > 127)).collect(),
+                width: image.width(),
+                height: image.height(),
+                background: None,
+            }
+        }
+    }
+
+    struct CountingEmitter;
+
+    impl Emitter for CountingEmitter {
+        fn emit(&self, document: &VectorDocument, _options: &VectorizeOptions) -> String {
+            format!("{} layers, {} paths", document.layers.len(), document.path_count())
+        }
+    }
+
+    #[test]
+    fn custom_stages_replace_defaults() {
+        let image = RgbaImage::from_fn(6, 6, |x, _| {
+            if x < 3 {
+                Rgba([10, 10, 10, 255])
+            } else {
+                Rgba([240, 240, 240, 255])
+            }
+        });
+        let pipeline = Pipeline::builder().quantizer(TwoTone).emitter(CountingEmitter).build();
+        assert_eq!(pipeline.run(&image, &VectorizeOptions::default()), "2 layers, 2 paths");
+    }
+
+    #[test]
+    fn custom_quantizer_replaces_the_embedded_palette() {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, 2, 2);
+            encoder.set_color(png::ColorType::Indexed);
+            encoder.set_palette(vec![200, 0, 0, 0, 0, 200]);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[0, 1, 1, 0]).unwrap();
+        }
+        let options = VectorizeOptions::default();
+        let svg = Pipeline::default().run_bytes(&bytes, &options).unwrap();
+        assert!(svg.contains("c80000"));
+
+        let svg = Pipeline::builder().quantizer(TwoTone).build().run_bytes(&bytes, &options).unwrap();
+        assert!(svg.contains("ffffff") && !svg.contains("c80000"));
+        let image = crate::decode_image(&bytes).unwrap().to_rgba8();
+        let pipeline = Pipeline::builder().quantizer(TwoTone).build();
+        let mut vectorizer = crate::Vectorizer::with_pipeline(image, options, pipeline)
+            .with_embedded_palette(decode_indexed(&bytes).unwrap().unwrap());
+        assert!(!vectorizer.render().contains("c80000"));
+    }
+
+    #[test]
+    fn document_groups_paths_by_palette_color() {
+        let image = RgbaImage::from_fn(8, 8, |_, y| {
+            if y < 4 {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0, 0, 255, 255])
+            }
+        });
+        let document = Pipeline::default().document(&image, &VectorizeOptions::default());
+        assert_eq!(document.width, 8);
+        assert_eq!(document.layers.len(), 2);
+        assert!(document.layers.iter().all(|layer| !layer.paths.is_empty()));
+    }
+}
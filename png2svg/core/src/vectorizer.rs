@@ -0,0 +1,346 @@
+use image::{DynamicImage, RgbaImage};
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use crate::pipeline::emit_document;
+use crate::{
+    classify_image, decode_image, decode_indexed, high_precision, palette_size_from_options, DebugArtifacts,
+    Classification, Emitter, Pipeline, QuantizedImage, Region, Rgba16Image, TracedRegion, VectorDocument, VectorizeError,
+    VectorizeMode, VectorizeOptions,
+};
+
+/// Stateful vectorization engine that keeps the decoded image and every intermediate stage.
+///
+/// An embedded palette (see [`with_embedded_palette`](Self::with_embedded_palette)) replaces
+/// quantization whenever it fits the requested palette size and the pipeline's quantizer
+/// [keeps embedded palettes](crate::Quantizer::keeps_embedded_palette).
+///
+/// Changing options only recomputes the stages whose inputs changed: `smoothness`, `tolerance`
+/// and `mode` re-run simplification and path building on the cached contours, while `colors`,
+/// `detail`, `alpha_threshold` and `background` start again from quantization.
+///
+/// [`VectorizeMode::Auto`] is resolved as soon as the options are set: the image is classified
+/// once, and [`options`](Self::options) returns the chosen mode and tuning.
+pub struct Vectorizer {
+    image: RgbaImage,
+    options: VectorizeOptions,
+    /// Kept after switching to an explicit mode, so switching back does not classify again.
+    classification: Option<Classification>,
+    /// Whether the current options asked for [`VectorizeMode::Auto`].
+    auto: bool,
+    pipeline: Pipeline,
+    /// 16-bit copy of `image` for sources with more than 8 bits per channel.
+    precise: Option<Rgba16Image>,
+    embedded_palette: Option<QuantizedImage>,
+    quantized: Option<QuantizedImage>,
+    regions: Option<Vec<Region>>,
+    contours: Option<Vec<TracedRegion>>,
+    document: Option<VectorDocument>,
+}
+
+impl Vectorizer {
+    pub fn new(image: RgbaImage, options: VectorizeOptions) -> Self {
+        Self::with_pipeline(image, options, Pipeline::default())
+    }
+
+    pub fn with_pipeline(image: RgbaImage, options: VectorizeOptions, pipeline: Pipeline) -> Self {
+        let classification = (options.mode == VectorizeMode::Auto).then(|| classify_image(&image));
+        let options = match &classification {
+            Some(classification) => classification.apply(&options),
+            None => options,
+        };
+        Self {
+            image,
+            options,
+            auto: classification.is_some(),
+            classification,
+            pipeline,
+            precise: None,
+            embedded_palette: None,
+            quantized: None,
+            regions: None,
+            contours: None,
+            document: None,
+        }
+    }
+
+    /// Keeps 16-bit and float sources at full precision for quantization.
+    pub fn from_image(image: &DynamicImage, options: VectorizeOptions) -> Self {
+        let mut vectorizer = Self::new(image.to_rgba8(), options);
+        vectorizer.precise = high_precision(image);
+        vectorizer
+    }
+
+    /// Decodes an image; paletted PNG/GIF input keeps its embedded palette.
+    pub fn from_png_bytes(png_bytes: &[u8], options: VectorizeOptions) -> Result<Self, VectorizeError> {
+        let embedded_palette = decode_indexed(png_bytes)?;
+        let image = decode_image(png_bytes)?;
+        let vectorizer = Self::from_image(&image, options);
+        Ok(match embedded_palette {
+            Some(quantized) => vectorizer.with_embedded_palette(quantized),
+            None => vectorizer,
+        })
+    }
+
+    /// Uses the image's own palette and indices (from [`decode_indexed`]) instead of quantizing,
+    /// as long as it uses no more colors than the options allow.
+    pub fn with_embedded_palette(mut self, quantized: QuantizedImage) -> Self {
+        if quantized.width == self.image.width() && quantized.height == self.image.height() {
+            self.embedded_palette = Some(quantized);
+            self.quantized = None;
+            self.regions = None;
+            self.contours = None;
+            self.document = None;
+        }
+        self
+    }
+
+    pub fn image(&self) -> &RgbaImage {
+        &self.image
+    }
+
+    pub fn options(&self) -> &VectorizeOptions {
+        &self.options
+    }
+
+    /// What [`VectorizeMode::Auto`] detected, and why; `None` unless the current options asked
+    /// for `Auto`.
+    pub fn classification(&self) -> Option<&Classification> {
+        self.classification.as_ref().filter(|_| self.auto)
+    }
+
+    /// Replaces the options, dropping only the cached stages that depend on what changed.
+    pub fn set_options(&mut self, options: VectorizeOptions) {
+        self.auto = options.mode == VectorizeMode::Auto;
+        let options = match options.mode {
+            VectorizeMode::Auto => self
+                .classification
+                .get_or_insert_with(|| classify_image(&self.image))
+                .apply(&options),
+            _ => options,
+        };
+        let palette_changed = palette_size_from_options(&options) != palette_size_from_options(&self.options)
+            || options.alpha_threshold != self.options.alpha_threshold
+            || options.background != self.options.background;
+        let paths_changed = options.mode != self.options.mode
+            || options.tolerance != self.options.tolerance
+            || options.smoothness != self.options.smoothness;
+
+        if palette_changed {
+            self.quantized = None;
+            self.regions = None;
+            self.contours = None;
+        }
+        if palette_changed || paths_changed {
+            self.document = None;
+        }
+        self.options = options;
+    }
+
+    /// Runs any stages that are not cached yet and returns the emitter's output (SVG by default).
+    pub fn render(&mut self) -> String {
+        self.ensure_document();
+        let document = self.document.as_ref().expect("document stage is cached");
+        self.pipeline.emit(document, &self.options)
+    }
+
+    /// Like [`render`](Self::render) but serializes the cached document with another emitter,
+    /// e.g. to export several formats without re-running any stage.
+    pub fn render_with(&mut self, emitter: &dyn Emitter) -> String {
+        self.ensure_document();
+        let document = self.document.as_ref().expect("document stage is cached");
+        emit_document(emitter, document, &self.options)
+    }
+
+    pub fn quantized(&mut self) -> &QuantizedImage {
+        self.ensure_quantized();
+        self.quantized.as_ref().expect("quantized stage is cached")
+    }
+
+    pub fn regions(&mut self) -> &[Region] {
+        self.ensure_contours();
+        self.regions.as_deref().expect("label stage is cached")
+    }
+
+    pub fn contours(&mut self) -> &[TracedRegion] {
+        self.ensure_contours();
+        self.contours.as_deref().expect("contour stage is cached")
+    }
+
+    pub fn document(&mut self) -> &VectorDocument {
+        self.ensure_document();
+        self.document.as_ref().expect("document stage is cached")
+    }
+
+    /// Encodes the quantized image, region label map and contour overlay for the current options.
+    pub fn debug_artifacts(&mut self) -> Result<DebugArtifacts, VectorizeError> {
+        self.ensure_document();
+        DebugArtifacts::new(
+            &self.image,
+            self.quantized.as_ref().expect("quantized stage is cached"),
+            self.regions.as_deref().expect("label stage is cached"),
+            self.contours.as_deref().expect("contour stage is cached"),
+            self.document.as_ref().expect("document stage is cached"),
+        )
+    }
+
+    fn ensure_quantized(&mut self) {
+        if self.quantized.is_none() {
+            let embedded = self
+                .embedded_palette
+                .as_ref()
+                .and_then(|embedded| self.pipeline.fit_embedded_palette(embedded, &self.options));
+            self.quantized = Some(match embedded {
+                Some(embedded) => embedded,
+                None => match &self.precise {
+                    Some(precise) => self.pipeline.quantize_rgba16(precise, &self.options),
+                    None => self.pipeline.quantize(&self.image, &self.options),
+                },
+            });
+        }
+    }
+
+    fn ensure_contours(&mut self) {
+        self.ensure_quantized();
+        let quantized = self.quantized.as_ref().expect("quantized stage is cached");
+        let regions = self.regions.get_or_insert_with(|| self.pipeline.label(quantized));
+        if self.contours.is_none() {
+            self.contours = Some(self.pipeline.trace(quantized, regions));
+        }
+    }
+
+    fn ensure_document(&mut self) {
+        self.ensure_contours();
+        if self.document.is_none() {
+            let quantized = self.quantized.as_ref().expect("quantized stage is cached");
+            let contours = self.contours.as_deref().expect("contour stage is cached");
+            self.document = Some(self.pipeline.build_document(quantized, contours, &self.options));
+        }
+    }
+}
+
+/// `Vectorizer` exposed to JavaScript as a class, for interactive previews.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_name = Vectorizer)]
+pub struct WasmVectorizer {
+    inner: Vectorizer,
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(js_class = Vectorizer)]
+impl WasmVectorizer {
+    /// Creates an engine from canvas pixels (`imageData.data`, `imageData.width`, `imageData.height`).
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        data: wasm_bindgen::Clamped<Vec<u8>>,
+        width: u32,
+        height: u32,
+        options_json: &str,
+    ) -> Result<WasmVectorizer, JsValue> {
+        let options = crate::options_from_json(options_json)?;
+        crate::check_buffer_len(width, height, 4, data.0.len()).map_err(|err| JsValue::from_str(&err.to_string()))?;
+        let image = RgbaImage::from_raw(width, height, data.0)
+            .ok_or_else(|| JsValue::from_str("could not wrap RGBA buffer"))?;
+        Ok(Self {
+            inner: Vectorizer::new(image, options),
+        })
+    }
+
+    /// Creates an engine from encoded image bytes.
+    #[wasm_bindgen(js_name = fromPng)]
+    pub fn from_png(png_bytes: &[u8], options_json: &str) -> Result<WasmVectorizer, JsValue> {
+        let options = crate::options_from_json(options_json)?;
+        let inner = Vectorizer::from_png_bytes(png_bytes, options).map_err(|err| JsValue::from_str(&err.to_string()))?;
+        Ok(Self { inner })
+    }
+
+    #[wasm_bindgen(js_name = setOptions)]
+    pub fn set_options(&mut self, options_json: &str) -> Result<(), JsValue> {
+        let options = crate::options_from_json(options_json)?;
+        self.inner.set_options(options);
+        Ok(())
+    }
+
+    /// JSON of the kind, metrics and reasons `auto` mode decided on, or `undefined`.
+    #[wasm_bindgen(js_name = classificationJson)]
+    pub fn classification_json(&self) -> Option<String> {
+        self.inner.classification().and_then(|classification| serde_json::to_string(classification).ok())
+    }
+
+    #[wasm_bindgen(js_name = renderSvg)]
+    pub fn render_svg(&mut self) -> String {
+        self.inner.render()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{vectorize_rgba_image, VectorizeMode};
+    use image::Rgba;
+
+    fn sample_image() -> RgbaImage {
+        RgbaImage::from_fn(12, 12, |x, y| {
+            let dx = x as i32 - 6;
+            let dy = y as i32 - 6;
+            if dx * dx + dy * dy < 16 {
+                Rgba([220, 30, 30, 255])
+            } else {
+                Rgba([250, 250, 250, 255])
+            }
+        })
+    }
+
+    #[test]
+    fn matches_one_shot_rendering() {
+        let options = VectorizeOptions::default();
+        let mut vectorizer = Vectorizer::new(sample_image(), options.clone());
+        let expected = vectorize_rgba_image(&sample_image(), &options);
+        assert_eq!(vectorizer.render(), expected);
+    }
+
+    #[test]
+    fn path_options_keep_cached_contours() {
+        let mut vectorizer = Vectorizer::new(sample_image(), VectorizeOptions::default());
+        vectorizer.render();
+
+        let options = VectorizeOptions {
+            mode: VectorizeMode::Poster,
+            tolerance: 3.0,
+            smoothness: 0.2,
+            ..VectorizeOptions::default()
+        };
+        vectorizer.set_options(options.clone());
+        assert!(vectorizer.contours.is_some());
+        assert!(vectorizer.document.is_none());
+
+        let expected = vectorize_rgba_image(&sample_image(), &options);
+        assert_eq!(vectorizer.render(), expected);
+
+        vectorizer.set_options(VectorizeOptions {
+            colors: 3,
+            ..options
+        });
+        assert!(vectorizer.quantized.is_none());
+        assert!(vectorizer.contours.is_none());
+    }
+
+    #[test]
+    fn auto_mode_is_resolved_once() {
+        let options = VectorizeOptions {
+            mode: VectorizeMode::Auto,
+            ..VectorizeOptions::default()
+        };
+        let mut vectorizer = Vectorizer::new(sample_image(), options.clone());
+        let classification = vectorizer.classification().cloned().unwrap();
+        assert_eq!(vectorizer.options().mode, classification.mode());
+        assert_eq!(vectorizer.render(), vectorize_rgba_image(&sample_image(), &options));
+
+        vectorizer.set_options(VectorizeOptions::default());
+        assert_eq!(vectorizer.options().mode, VectorizeMode::Logo);
+        assert_eq!(vectorizer.classification(), None);
+        vectorizer.set_options(options);
+        assert_eq!(vectorizer.classification(), Some(&classification));
+    }
+}
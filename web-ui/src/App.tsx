@@ -2,6 +2,7 @@ import { ChangeEvent, useEffect, useMemo, useState } from 'react';
 import clsx from 'clsx';
 
 type WasmModule = typeof import('./pkg/png2svg_core.js');
+type WasmVectorizer = InstanceType<WasmModule['Vectorizer']>;
 
 const MAX_COLORS = 32;
 
@@ -57,6 +58,21 @@ function generatePlaceholderSvg(options: UiOptions, width = 420, height = 280) {
   }">${blocks.join('')}</svg>`;
 }
 
+async function decodeToImageData(file: File): Promise<ImageData> {
+  const bitmap = await createImageBitmap(file);
+  try {
+    const canvas = document.createElement('canvas');
+    canvas.width = bitmap.width;
+    canvas.height = bitmap.height;
+    const context = canvas.getContext('2d');
+    if (!context) throw new Error('2D canvas is not available');
+    context.drawImage(bitmap, 0, 0);
+    return context.getImageData(0, 0, bitmap.width, bitmap.height);
+  } finally {
+    bitmap.close();
+  }
+}
+
 function formatPercent(value: number) {
   return `${Math.round(value * 100)}%`;
 }
@@ -67,6 +83,7 @@ function App() {
   const [pngPreviewUrl, setPngPreviewUrl] = useState<string | null>(null);
   const [svgMarkup, setSvgMarkup] = useState<string>('');
   const [wasmModule, setWasmModule] = useState<WasmModule | null>(null);
+  const [engine, setEngine] = useState<WasmVectorizer | null>(null);
   const [wasmReady, setWasmReady] = useState(false);
   const [wasmError, setWasmError] = useState<string | null>(null);
   const [isVectorizing, setIsVectorizing] = useState(false);
@@ -130,64 +147,82 @@ function App() {
     });
   }
 
+  // Decode once per file; the engine caches quantization and contours so slider changes stay cheap.
   useEffect(() => {
     if (!wasmReady || !wasmModule || !selectedFile) return;
 
     const currentFile = selectedFile;
     const currentModule = wasmModule;
     let cancelled = false;
+    let created: WasmVectorizer | null = null;
     setIsVectorizing(true);
     setVectorizeError(null);
 
-    async function runVectorizer() {
+    async function loadEngine() {
       try {
-        const buffer = await currentFile.arrayBuffer();
-        const optionsJson = JSON.stringify(options);
-        console.log('[open-vectorizer] vectorizing with options:', optionsJson);
-        const svg = currentModule.png_to_svg_wasm(new Uint8Array(buffer), optionsJson);
-        console.log('[open-vectorizer] generated SVG length:', svg.length);
-        console.log('[open-vectorizer] SVG preview (first 500 chars):', svg.substring(0, 500));
-        
-        // Check for unique colors in the SVG
-        const colorMatches = svg.matchAll(/fill="#([0-9a-f]{6})"/gi);
-        const uniqueColors = new Set<string>();
-        let colorCount = 0;
-        for (const match of colorMatches) {
-          uniqueColors.add(match[1].toLowerCase());
-          colorCount++;
-          if (colorCount > 1000) break; // Sample first 1000 to avoid performance issues
-        }
-        console.log('[open-vectorizer] unique colors found (sampled):', uniqueColors.size, 'colors:', Array.from(uniqueColors).slice(0, 10));
-        
-        // Check viewBox dimensions
-        const viewBoxMatch = svg.match(/viewBox="0 0 (\d+) (\d+)"/);
-        if (viewBoxMatch) {
-          console.log('[open-vectorizer] SVG dimensions:', viewBoxMatch[1], 'x', viewBoxMatch[2]);
-        }
-        
-        if (!cancelled) {
-          setSvgMarkup(svg);
-        }
+        const imageData = await decodeToImageData(currentFile);
+        if (cancelled) return;
+        created = new currentModule.Vectorizer(imageData.data, imageData.width, imageData.height, '');
+        setEngine(created);
       } catch (error) {
-        console.error('[open-vectorizer] vectorization failed', error);
+        console.error('[open-vectorizer] failed to decode input', error);
         if (!cancelled) {
           const message = error instanceof Error ? error.message : 'Unknown error';
           setVectorizeError(`Vectorization failed: ${message}`);
-          setSvgMarkup(generatePlaceholderSvg(options));
-        }
-      } finally {
-        if (!cancelled) {
           setIsVectorizing(false);
         }
       }
     }
 
-    runVectorizer();
+    loadEngine();
 
     return () => {
       cancelled = true;
+      setEngine(null);
+      created?.free();
     };
-  }, [options, selectedFile, wasmModule, wasmReady]);
+  }, [selectedFile, wasmModule, wasmReady]);
+
+  useEffect(() => {
+    if (!engine) return;
+
+    setIsVectorizing(true);
+    setVectorizeError(null);
+    try {
+      const optionsJson = JSON.stringify(options);
+      console.log('[open-vectorizer] vectorizing with options:', optionsJson);
+      engine.setOptions(optionsJson);
+      const svg = engine.renderSvg();
+      console.log('[open-vectorizer] generated SVG length:', svg.length);
+      console.log('[open-vectorizer] SVG preview (first 500 chars):', svg.substring(0, 500));
+
+      // Check for unique colors in the SVG
+      const colorMatches = svg.matchAll(/fill="#([0-9a-f]{6})"/gi);
+      const uniqueColors = new Set<string>();
+      let colorCount = 0;
+      for (const match of colorMatches) {
+        uniqueColors.add(match[1].toLowerCase());
+        colorCount++;
+        if (colorCount > 1000) break; // Sample first 1000 to avoid performance issues
+      }
+      console.log('[open-vectorizer] unique colors found (sampled):', uniqueColors.size, 'colors:', Array.from(uniqueColors).slice(0, 10));
+
+      // Check viewBox dimensions
+      const viewBoxMatch = svg.match(/viewBox="0 0 (\d+) (\d+)"/);
+      if (viewBoxMatch) {
+        console.log('[open-vectorizer] SVG dimensions:', viewBoxMatch[1], 'x', viewBoxMatch[2]);
+      }
+
+      setSvgMarkup(svg);
+    } catch (error) {
+      console.error('[open-vectorizer] vectorization failed', error);
+      const message = error instanceof Error ? error.message : 'Unknown error';
+      setVectorizeError(`Vectorization failed: ${message}`);
+      setSvgMarkup(generatePlaceholderSvg(options));
+    } finally {
+      setIsVectorizing(false);
+    }
+  }, [engine, options]);
 
   function updateOption<K extends keyof UiOptions>(key: K, value: UiOptions[K]) {
     setOptions((prev) => ({ ...prev, [key]: value }));
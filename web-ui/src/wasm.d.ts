@@ -1,5 +1,14 @@
 declare module '/pkg/png2svg_core.js' {
   export function png_to_svg_wasm(png_bytes: Uint8Array, options_json: string): string;
+  export function vectorize_rgba_wasm(data: Uint8ClampedArray, width: number, height: number, options_json: string): string;
+  export function vectorize_image_data_wasm(image_data: ImageData, options_json: string): string;
   export function default_options_json(): string;
+  export class Vectorizer {
+    constructor(data: Uint8ClampedArray, width: number, height: number, options_json: string);
+    static fromPng(png_bytes: Uint8Array, options_json: string): Vectorizer;
+    setOptions(options_json: string): void;
+    renderSvg(): string;
+    free(): void;
+  }
   export default function init(module?: WebAssembly.Module | RequestInfo | URL): Promise<unknown>;
 }